@@ -0,0 +1,554 @@
+//! A [`SearchBackend`] that combines the results of several others, e.g. to serve one Torznab endpoint over several sources
+//!
+//! Each search is sent to all the backends at once, and each one has a timeout, so a slow or broken source doesn't hold up (or fail) the whole search.
+//! The results are interleaved (the first result of each backend, then the second of each, etc.) and deduplicated, keeping the first backend's copy: torrents are duplicates if they have the same infohash (from the `infohash` attribute or the magnet URI), or, if either's infohash is unknown, the same title (ignoring case) and size.
+//!
+//! Backends that fail or time out are left out of the results, and reported to [`on_failure`](Aggregator::on_failure); the search only fails if all of them do.
+//! To get the failures along with the results instead, use [`Aggregator::search_all`].
+//!
+//! Example, combining a local catalog with another indexer:
+//! ```
+//! use std::time::Duration;
+//! use torznab_toolkit::aggregator::Aggregator;
+//! use torznab_toolkit::catalog::Catalog;
+//! # use async_trait::async_trait;
+//! # use torznab_toolkit::client::{Client, HttpClient, HttpResponse};
+//! # struct Http;
+//! # #[async_trait]
+//! # impl HttpClient for Http {
+//! #     async fn get(&self, _url: &str) -> Result<HttpResponse, String> { unimplemented!() }
+//! # }
+//!
+//! let aggregator = Aggregator::new()
+//!     .backend("local", Catalog::new(vec![/* ... */]))
+//!     .backend_with_timeout(
+//!         "remote",
+//!         Client::new("http://localhost:9117/api", Http).apikey("letmein"),
+//!         Duration::from_secs(30),
+//!     )
+//!     .on_failure(|failures| {
+//!         for failure in failures {
+//!             eprintln!("search failed: {}", failure);
+//!         }
+//!     });
+//! ```
+use crate::data::{SearchBackend, SearchParameters, SearchResults, Torrent, TorznabError};
+use async_trait::async_trait;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinSet;
+
+/// The timeout for backends that don't have their own, unless it's changed with [`Aggregator::timeout`]
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, PartialEq)]
+/// Why a backend's results were left out
+pub enum BackendError {
+    /// The backend didn't respond within its timeout; contains the timeout
+    TimedOut(Duration),
+    /// The backend returned an error
+    Failed(TorznabError),
+    /// The backend panicked
+    Panicked,
+    /// The backend's search was cancelled before it finished, e.g. because the runtime is shutting down
+    Cancelled,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// A backend that failed during a search
+pub struct BackendFailure {
+    /// The backend's name, as given to [`Aggregator::backend`]
+    pub backend: String,
+    /// What went wrong
+    pub error: BackendError,
+}
+
+impl fmt::Display for BackendFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.error {
+            BackendError::TimedOut(timeout) => {
+                return write!(f, "`{}` timed out after {:?}", self.backend, timeout);
+            }
+            BackendError::Failed(e) => {
+                return write!(f, "`{}` failed: {}", self.backend, e);
+            }
+            BackendError::Panicked => {
+                return write!(f, "`{}` panicked", self.backend);
+            }
+            BackendError::Cancelled => {
+                return write!(f, "`{}` was cancelled", self.backend);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// The combined results of a search, from [`Aggregator::search_all`]
+pub struct AggregatedResults {
+    /// The interleaved and deduplicated torrents, from the start (i.e. without the search's `offset` applied)
+    pub torrents: Vec<Torrent>,
+    /// The total number of matching torrents across the backends that succeeded, less the duplicates that were found
+    ///
+    /// Backends only return the results up to the requested page, so duplicates past that can't be found; this is the best estimate there is.
+    pub total: u64,
+    /// The backends that failed, in the order they were added
+    pub failures: Vec<BackendFailure>,
+}
+
+/// A function that's called with the backends that failed during a search; see [`Aggregator::on_failure`]
+type FailureHandler = dyn Fn(&[BackendFailure]) + Send + Sync;
+
+/// A backend of an [`Aggregator`]
+#[derive(Clone)]
+struct Source {
+    /// The name it's reported with
+    name: String,
+    /// The backend itself
+    backend: Arc<dyn SearchBackend>,
+    /// Its own timeout, if it has one
+    timeout: Option<Duration>,
+}
+
+#[derive(Clone)]
+/// A [`SearchBackend`] that sends each search to several backends concurrently, and combines their results; see the [module docs](self)
+pub struct Aggregator {
+    /// The backends, in the order they were added
+    sources: Vec<Source>,
+    /// The timeout for backends without their own
+    timeout: Duration,
+    /// Called with the backends that failed during a search
+    on_failure: Option<Arc<FailureHandler>>,
+}
+
+impl Default for Aggregator {
+    fn default() -> Aggregator {
+        return Aggregator {
+            sources: Vec::new(),
+            timeout: DEFAULT_TIMEOUT,
+            on_failure: None,
+        };
+    }
+}
+
+impl Aggregator {
+    /// Creates an aggregator with no backends
+    pub fn new() -> Aggregator {
+        return Aggregator::default();
+    }
+
+    /// Adds a backend, with the default timeout; `name` is what it's reported as if it fails
+    ///
+    /// Backends are listed first in the results in the order they were added.
+    pub fn backend(
+        mut self,
+        name: impl AsRef<str>,
+        backend: impl SearchBackend + 'static,
+    ) -> Aggregator {
+        self.sources.push(Source {
+            name: name.as_ref().to_string(),
+            backend: Arc::new(backend),
+            timeout: None,
+        });
+        return self;
+    }
+
+    /// Adds a backend like [`backend`](Aggregator::backend), with its own timeout
+    pub fn backend_with_timeout(
+        mut self,
+        name: impl AsRef<str>,
+        backend: impl SearchBackend + 'static,
+        timeout: Duration,
+    ) -> Aggregator {
+        self.sources.push(Source {
+            name: name.as_ref().to_string(),
+            backend: Arc::new(backend),
+            timeout: Some(timeout),
+        });
+        return self;
+    }
+
+    /// Sets the timeout for backends that don't have their own; defaults to [`DEFAULT_TIMEOUT`]
+    pub fn timeout(mut self, timeout: Duration) -> Aggregator {
+        self.timeout = timeout;
+        return self;
+    }
+
+    /// Sets a function to call with the backends that failed, whenever any do during a search (e.g. to log them)
+    ///
+    /// When the aggregator is used as a [`SearchBackend`], the search still succeeds with the other backends' results, so this is the only place these failures show up.
+    pub fn on_failure(
+        mut self,
+        on_failure: impl Fn(&[BackendFailure]) + Send + Sync + 'static,
+    ) -> Aggregator {
+        self.on_failure = Some(Arc::new(on_failure));
+        return self;
+    }
+
+    /// Searches all the backends concurrently, returning their combined results along with the ones that failed
+    ///
+    /// Each backend is asked for the results up to the end of the requested page (i.e. with no `offset`, and a `limit` of `offset + limit`), so the combined results can be paged.
+    /// This needs to be called within a Tokio runtime, since each backend's search is spawned as a task; if the returned future is dropped, the searches are cancelled.
+    pub async fn search_all(&self, parameters: SearchParameters) -> AggregatedResults {
+        let mut child_parameters = parameters.clone();
+        child_parameters.offset = None;
+        child_parameters.limit = parameters
+            .limit
+            .saturating_add(parameters.offset.unwrap_or(0));
+
+        let mut tasks = JoinSet::new();
+        let mut indexes = HashMap::new();
+        for (index, source) in self.sources.iter().enumerate() {
+            let backend = source.backend.clone();
+            let timeout = source.timeout.unwrap_or(self.timeout);
+            let parameters = child_parameters.clone();
+            let handle = tasks.spawn(async move {
+                match tokio::time::timeout(timeout, backend.search(parameters)).await {
+                    Ok(Ok(results)) => {
+                        return Ok(results);
+                    }
+                    Ok(Err(e)) => {
+                        return Err(BackendError::Failed(e));
+                    }
+                    Err(_) => {
+                        return Err(BackendError::TimedOut(timeout));
+                    }
+                }
+            });
+            indexes.insert(handle.id(), index);
+        }
+
+        let mut results: Vec<Option<Result<SearchResults, BackendError>>> =
+            vec![None; self.sources.len()];
+        while let Some(joined) = tasks.join_next_with_id().await {
+            match joined {
+                Ok((id, result)) => {
+                    results[indexes[&id]] = Some(result);
+                }
+                Err(e) => {
+                    let error = if e.is_panic() {
+                        BackendError::Panicked
+                    } else {
+                        BackendError::Cancelled
+                    };
+                    results[indexes[&e.id()]] = Some(Err(error));
+                }
+            }
+        }
+
+        let mut pages = Vec::new();
+        let mut total: u64 = 0;
+        let mut failures = Vec::new();
+        for (source, result) in self.sources.iter().zip(results) {
+            // every task is joined, so there's always a result
+            match result.unwrap() {
+                Ok(SearchResults::All(torrents)) => {
+                    total += torrents.len() as u64;
+                    pages.push(torrents);
+                }
+                Ok(SearchResults::Paged {
+                    torrents,
+                    total: page_total,
+                }) => {
+                    total += page_total.max(torrents.len() as u64);
+                    pages.push(torrents);
+                }
+                Err(error) => {
+                    failures.push(BackendFailure {
+                        backend: source.name.clone(),
+                        error,
+                    });
+                }
+            }
+        }
+
+        let (torrents, duplicates) = merge(pages);
+        return AggregatedResults {
+            total: total.saturating_sub(duplicates).max(torrents.len() as u64),
+            torrents,
+            failures,
+        };
+    }
+}
+
+impl fmt::Debug for Aggregator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let backends: Vec<&str> = self
+            .sources
+            .iter()
+            .map(|source| source.name.as_str())
+            .collect();
+        return f
+            .debug_struct("Aggregator")
+            .field("backends", &backends)
+            .field("timeout", &self.timeout)
+            .finish_non_exhaustive();
+    }
+}
+
+#[async_trait]
+impl SearchBackend for Aggregator {
+    /// Searches all the backends with [`Aggregator::search_all`], and returns the requested page of the combined results
+    ///
+    /// Failures are passed to [`on_failure`](Aggregator::on_failure); if every backend fails, so does the search, with [`TorznabError::Unknown`] listing the failures.
+    async fn search(&self, parameters: SearchParameters) -> Result<SearchResults, TorznabError> {
+        let offset = parameters.offset.unwrap_or(0) as usize;
+        let limit = parameters.limit as usize;
+        let results = self.search_all(parameters).await;
+
+        if !results.failures.is_empty() {
+            if let Some(on_failure) = &self.on_failure {
+                on_failure(&results.failures);
+            }
+            if results.failures.len() == self.sources.len() {
+                let failures: Vec<String> = results
+                    .failures
+                    .iter()
+                    .map(|failure| failure.to_string())
+                    .collect();
+                return Err(TorznabError::Unknown(format!(
+                    "all backends failed: {}",
+                    failures.join("; ")
+                )));
+            }
+        }
+
+        return Ok(SearchResults::Paged {
+            torrents: results
+                .torrents
+                .into_iter()
+                .skip(offset)
+                .take(limit)
+                .collect(),
+            total: results.total,
+        });
+    }
+}
+
+/// Interleaves the backends' results and removes the duplicates, returning the merged results and how many duplicates there were
+fn merge(pages: Vec<Vec<Torrent>>) -> (Vec<Torrent>, u64) {
+    let mut merged = Vec::new();
+    let mut duplicates = 0;
+    let mut infohashes = HashSet::new();
+    let mut titles_and_sizes = HashSet::new();
+    // titles and sizes of the kept torrents without an infohash, which anything with the same title and size is a duplicate of
+    let mut titles_and_sizes_without_infohash = HashSet::new();
+
+    let mut pages: Vec<std::vec::IntoIter<Torrent>> =
+        pages.into_iter().map(|page| page.into_iter()).collect();
+    loop {
+        let mut any = false;
+        for page in pages.iter_mut() {
+            let torrent = match page.next() {
+                Some(torrent) => torrent,
+                None => continue,
+            };
+            any = true;
+
+            let infohash = infohash(&torrent);
+            let title_and_size = (torrent.title.to_lowercase(), torrent.size);
+            // torrents with different infohashes aren't duplicates, even if their title and size match
+            let duplicate = match &infohash {
+                Some(infohash) => {
+                    infohashes.contains(infohash)
+                        || titles_and_sizes_without_infohash.contains(&title_and_size)
+                }
+                None => titles_and_sizes.contains(&title_and_size),
+            };
+            if duplicate {
+                duplicates += 1;
+                continue;
+            }
+
+            match infohash {
+                Some(infohash) => {
+                    infohashes.insert(infohash);
+                }
+                None => {
+                    titles_and_sizes_without_infohash.insert(title_and_size.clone());
+                }
+            }
+            titles_and_sizes.insert(title_and_size);
+            merged.push(torrent);
+        }
+        if !any {
+            return (merged, duplicates);
+        }
+    }
+}
+
+/// Returns a torrent's infohash, in lowercase, from its `infohash` attribute or its magnet URI
+fn infohash(torrent: &Torrent) -> Option<String> {
+    if let Some(infohash) = &torrent.attributes.infohash {
+        return Some(infohash.trim().to_lowercase());
+    }
+    let magnet_uri = torrent.magnet_uri.as_ref()?;
+    let start = magnet_uri.find("xt=urn:btih:")? + "xt=urn:btih:".len();
+    let infohash = magnet_uri[start..].split('&').next()?;
+    return Some(infohash.to_lowercase());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::Catalog;
+    use crate::data::TorrentAttributes;
+    use std::sync::Mutex;
+
+    fn torrent(title: &str, size: u64, infohash: &str) -> Torrent {
+        return Torrent::builder(title, size)
+            .category(5000)
+            .magnet_uri(format!("magnet:?xt=urn:btih:{}&dn=x", infohash))
+            .build()
+            .unwrap();
+    }
+
+    fn without_infohash(title: &str, size: u64) -> Torrent {
+        return Torrent::builder(title, size)
+            .category(5000)
+            .torrent_file_url("http://localhost/x.torrent")
+            .build()
+            .unwrap();
+    }
+
+    struct Slow;
+
+    #[async_trait]
+    impl SearchBackend for Slow {
+        async fn search(&self, _: SearchParameters) -> Result<SearchResults, TorznabError> {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            return Ok(SearchResults::All(vec![]));
+        }
+    }
+
+    fn failing(_: SearchParameters) -> Result<Vec<Torrent>, String> {
+        return Err("broken".to_string());
+    }
+
+    fn panicking(_: SearchParameters) -> Result<Vec<Torrent>, String> {
+        panic!("oops");
+    }
+
+    #[actix_rt::test]
+    async fn merges_and_deduplicates() {
+        let mut with_attribute = torrent("A (again)", 1, "ffff");
+        with_attribute.attributes = TorrentAttributes {
+            infohash: Some("AAAA".to_string()),
+            ..Default::default()
+        };
+        let aggregator = Aggregator::new()
+            .backend(
+                "first",
+                Catalog::new(vec![torrent("A", 1, "aaaa"), torrent("B", 2, "bbbb")]),
+            )
+            .backend(
+                "second",
+                Catalog::new(vec![
+                    with_attribute,           // same infohash as A
+                    without_infohash("b", 2), // same title and size as B
+                    torrent("C", 3, "dddd"),
+                ]),
+            );
+
+        let results = aggregator
+            .search_all(SearchParameters::new("search", 10))
+            .await;
+        let titles: Vec<&str> = results.torrents.iter().map(|t| t.title.as_str()).collect();
+        assert_eq!(titles, vec!["A", "B", "C"]);
+        assert_eq!(results.total, 3);
+        assert!(results.failures.is_empty());
+
+        let mut parameters = SearchParameters::new("search", 1);
+        parameters.offset = Some(1);
+        assert_eq!(
+            aggregator.search(parameters).await,
+            Ok(SearchResults::Paged {
+                torrents: vec![torrent("B", 2, "bbbb")],
+                total: 3,
+            })
+        );
+    }
+
+    #[actix_rt::test]
+    async fn title_and_size_only_count_without_infohashes() {
+        let aggregator = Aggregator::new()
+            .backend(
+                "first",
+                Catalog::new(vec![torrent("A", 1, "aaaa"), without_infohash("B", 2)]),
+            )
+            .backend(
+                "second",
+                Catalog::new(vec![
+                    torrent("a", 1, "ffff"), // same title and size as A, but a different infohash
+                    torrent("b", 2, "bbbb"), // same title and size as B, which has no infohash
+                    without_infohash("A", 1), // same title and size as A
+                ]),
+            );
+
+        let results = aggregator
+            .search_all(SearchParameters::new("search", 10))
+            .await;
+        let titles: Vec<&str> = results.torrents.iter().map(|t| t.title.as_str()).collect();
+        assert_eq!(titles, vec!["A", "a", "B"]);
+        assert_eq!(results.total, 3);
+    }
+
+    #[actix_rt::test]
+    async fn partial_results_with_failures() {
+        let reported = Arc::new(Mutex::new(Vec::new()));
+        let report = reported.clone();
+        let aggregator = Aggregator::new()
+            .timeout(Duration::from_millis(100))
+            .backend("slow", Slow)
+            .backend("failing", failing)
+            .backend("ok", Catalog::new(vec![torrent("A", 1, "aaaa")]))
+            .backend("panicking", panicking)
+            .on_failure(move |failures| {
+                report.lock().unwrap().extend_from_slice(failures);
+            });
+
+        let results = aggregator
+            .search(SearchParameters::new("search", 10))
+            .await
+            .unwrap();
+        assert_eq!(
+            results,
+            SearchResults::Paged {
+                torrents: vec![torrent("A", 1, "aaaa")],
+                total: 1,
+            }
+        );
+        assert_eq!(
+            *reported.lock().unwrap(),
+            vec![
+                BackendFailure {
+                    backend: "slow".to_string(),
+                    error: BackendError::TimedOut(Duration::from_millis(100)),
+                },
+                BackendFailure {
+                    backend: "failing".to_string(),
+                    error: BackendError::Failed(TorznabError::Unknown("broken".to_string())),
+                },
+                BackendFailure {
+                    backend: "panicking".to_string(),
+                    error: BackendError::Panicked,
+                },
+            ]
+        );
+    }
+
+    #[actix_rt::test]
+    async fn fails_if_every_backend_does() {
+        let aggregator = Aggregator::new()
+            .backend("failing", failing)
+            .backend_with_timeout("slow", Slow, Duration::from_millis(50));
+        assert_eq!(
+            aggregator.search(SearchParameters::new("search", 10)).await,
+            Err(TorznabError::Unknown(
+                "all backends failed: `failing` failed: 900 - broken; `slow` timed out after 50ms"
+                    .to_string()
+            ))
+        );
+    }
+}
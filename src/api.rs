@@ -1,26 +1,231 @@
-//! Contains the actual Torznab API
+//! The Torznab API itself, independent of any web framework
+//!
+//! [`handle`] takes a request's query parameters and headers, and returns the response's status, content type, and body; the Rocket routes are just a thin adapter over it, so it can also be used with other frameworks (or called directly in tests).
+//!
+//! Example, with some made-up framework:
+//! ```
+//! # use torznab_toolkit::data::Config;
+//! use torznab_toolkit::api::{handle, ApiRequest};
+//!
+//! async fn on_request(conf: &Config, query_string: &str, headers: Vec<(String, String)>) -> (u16, String) {
+//!     let mut request = ApiRequest::from_query_string(query_string);
+//!     request.headers = headers;
+//!
+//!     let response = handle(conf, request).await;
+//!     return (response.status, response.body);
+//! }
+//! ```
 use crate::data::*;
-use rocket::http::Status;
-use rocket::response::status;
-use rocket::{get, response::content::RawXml};
-use rocket::{FromForm, State};
+use percent_encoding::percent_decode_str;
 use std::borrow::Borrow;
+use std::net::IpAddr;
 use std::str;
 use xml::writer::{EmitterConfig, XmlEvent};
 
-#[derive(Debug, Clone, PartialEq, Eq, FromForm)]
+/// The content type of all of the API's responses
+pub const CONTENT_TYPE: &str = "text/xml; charset=utf-8";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+/// A request to the API, as passed to [`handle`]
+pub struct ApiRequest {
+    /// The URL-decoded query parameters, as `(name, value)` pairs
+    ///
+    /// If a parameter is given more than once, the first one is used.
+    pub query: Vec<(String, String)>,
+    /// The request's headers, as `(name, value)` pairs; these are passed on to the [`Authenticator`]
+    pub headers: Vec<(String, String)>,
+    /// The IP address of the client, if known; this is passed on to the [`Authenticator`]
+    pub client_ip: Option<IpAddr>,
+}
+
+impl ApiRequest {
+    /// Creates a request from a raw (URL-encoded) query string, e.g. `t=search&q=some+thing`, with no headers or client IP
+    ///
+    /// A leading `?` is ignored, as are empty `&`-separated segments.
+    pub fn from_query_string(query: impl AsRef<str>) -> ApiRequest {
+        let decode = |value: &str| -> String {
+            return percent_decode_str(&value.replace('+', " "))
+                .decode_utf8_lossy()
+                .to_string();
+        };
+
+        let query = query
+            .as_ref()
+            .trim_start_matches('?')
+            .split('&')
+            .filter(|segment| !segment.is_empty())
+            .map(|segment| match segment.split_once('=') {
+                Some((name, value)) => (decode(name), decode(value)),
+                None => (decode(segment), String::new()),
+            })
+            .collect();
+
+        return ApiRequest {
+            query,
+            headers: Vec::new(),
+            client_ip: None,
+        };
+    }
+
+    /// Returns the value of the query parameter `name`, if it was given
+    pub fn param(&self, name: &str) -> Option<&str> {
+        return self
+            .query
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| value.as_str());
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The API's response to an [`ApiRequest`], as returned by [`handle`]
+pub struct ApiResponse {
+    /// The HTTP status code
+    pub status: u16,
+    /// The value of the `Content-Type` header; currently always [`CONTENT_TYPE`]
+    pub content_type: &'static str,
+    /// The response body, which is XML (either a result, or a Torznab `<error>`)
+    pub body: String,
+}
+
+impl ApiResponse {
+    /// Creates a `200 OK` response with an XML body
+    fn ok(body: String) -> ApiResponse {
+        return ApiResponse {
+            status: 200,
+            content_type: CONTENT_TYPE,
+            body,
+        };
+    }
+}
+
+/// Handles a request to the API, dispatching on its `t` parameter
+///
+/// `t=caps` returns the capabilities, and `t=search`/`tvsearch`/`movie`/`music`/`book` run the search backend (after authenticating the request, if there's an [`Authenticator`]).
+/// Errors are returned as Torznab `<error>` responses, with a matching HTTP status (see [`TorznabError::http_status`]); a missing `t` is error `200`, an unknown one is `202`, and a search that isn't listed in [`Caps::searching`] (or isn't available) is `203`.
+///
+/// Searches are authenticated before their parameters are checked, so unauthorized requests always get error `100`.
+///
+/// Note that this doesn't check the config; that's done by [`attach`](crate::attach) and co., or with [`Config::validate`].
+pub async fn handle(conf: &Config, request: ApiRequest) -> ApiResponse {
+    let search_type = match request.param("t") {
+        Some("caps") => {
+            return caps(conf);
+        }
+        Some("search") => "search",
+        Some("tvsearch") => "tv-search",
+        Some("movie") => "movie-search",
+        Some("music") => "audio-search",
+        Some("book") => "book-search",
+        Some(_) => {
+            return error_response(TorznabError::NoSuchFunction);
+        }
+        None => {
+            return error_response(TorznabError::MissingParameter("t".to_string()));
+        }
+    };
+
+    // only searches that are listed in the caps *and* available can be used
+    let search_info = match conf.caps.search_info(search_type) {
+        Some(info) if info.available => info,
+        _ => {
+            return error_response(TorznabError::FunctionNotAvailable);
+        }
+    };
+
+    // authenticate first, so unauthorized clients can't probe which parameters are valid
+    let principal = match authenticate(conf, search_type, &request).await {
+        Ok(principal) => principal,
+        Err(error) => {
+            return error_response(error);
+        }
+    };
+
+    let request = match check_supported_params(conf.unsupported_params, search_info, request) {
+        Ok(request) => request,
+        Err(error) => {
+            return error_response(error);
+        }
+    };
+
+    let form = SearchForm::from_request(&request);
+    let mut parameters = match form.to_parameters(conf, search_type) {
+        Ok(parameters) => parameters,
+        Err(error) => {
+            return error_response(error);
+        }
+    };
+
+    parameters.principal = principal;
+
+    return search_handler(conf, parameters).await;
+}
+
+/// The query parameters of searches that are subject to [`Config::unsupported_params`], i.e. the ones that can be listed in `supportedParams`
+const SEARCH_PARAMS: [&str; 18] = [
+    "q",
+    "season",
+    "ep",
+    "tvdbid",
+    "rid",
+    "tvmazeid",
+    "imdbid",
+    "tmdbid",
+    "traktid",
+    "year",
+    "genre",
+    "artist",
+    "album",
+    "label",
+    "track",
+    "author",
+    "title",
+    "publisher",
+];
+
+/// Applies [`Config::unsupported_params`] to a search request, for the parameters that aren't in `search_info`'s `supported_params`
+///
+/// Returns the request without them (for [`UnsupportedParams::Strip`]), or an error for the first one (for [`UnsupportedParams::Reject`])
+fn check_supported_params(
+    policy: UnsupportedParams,
+    search_info: &SearchInfo,
+    mut request: ApiRequest,
+) -> Result<ApiRequest, TorznabError> {
+    let is_unsupported = |(name, value): &(String, String)| -> bool {
+        return SEARCH_PARAMS.contains(&name.as_str())
+            && !value.trim().is_empty()
+            && !search_info
+                .supported_params
+                .iter()
+                .any(|param| param.trim() == name);
+    };
+
+    match policy {
+        UnsupportedParams::Allow => {}
+        UnsupportedParams::Strip => {
+            request.query.retain(|pair| !is_unsupported(pair));
+        }
+        UnsupportedParams::Reject => {
+            if let Some((name, _)) = request.query.iter().find(|pair| is_unsupported(pair)) {
+                return Err(TorznabError::IncorrectParameter(name.clone()));
+            }
+        }
+    }
+    return Ok(request);
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
 /// A struct used by the API's search functions to hold its query parameters
-/// Currently required (AFAIK) because of limitations with rocket
 struct SearchForm {
     /// The text query for the search
     q: Option<String>,
     /// The apikey, for authentication
     apikey: Option<String>,
     /// The list of numeric category IDs to be included in the search results
-    /// Returned by Rocket.rs as a string of comma-separated values, then split in the function to a `Vec<u32>`
+    /// Given as a string of comma-separated values, then split in the function to a `Vec<u32>`
     cat: Option<String>,
     /// The list of extended attribute names to be included in the search results
-    /// Returned by Rocket.rs as a string of comma-separated values, then split in the function to a `Vec<String>`
+    /// Given as a string of comma-separated values, then split in the function to a `Vec<String>`
     attrs: Option<String>,
     /// Whether *all* extended attributes should be included in the search results; overrules `attrs`
     /// Can be 0 or 1
@@ -29,15 +234,87 @@ struct SearchForm {
     offset: Option<u32>,
     /// The maximum number of items to return - also limited to whatever `limits` is in [`Caps`]
     limit: Option<u32>,
+    /// The season number, or the year for daily shows (TV search only)
+    ///
+    /// This and the other type-specific fields are `String`s so that invalid values can be reported as an error, rather than being silently ignored like `offset` and `limit`
+    season: Option<String>,
+    /// The episode; a number, or `MM/DD` for daily shows (TV search only)
+    ep: Option<String>,
+    /// The TheTVDB ID (TV search only)
+    tvdbid: Option<String>,
+    /// The TVRage ID (TV search only)
+    rid: Option<String>,
+    /// The TVmaze ID (TV search only)
+    tvmazeid: Option<String>,
+    /// The IMDb ID (TV and movie search only)
+    imdbid: Option<String>,
+    /// The TMDb ID (TV and movie search only)
+    tmdbid: Option<String>,
+    /// The Trakt ID (TV search only)
+    traktid: Option<String>,
+    /// The release year (movie and music search only)
+    year: Option<String>,
+    /// The genre (movie search only)
+    genre: Option<String>,
+    /// The artist (music search only)
+    artist: Option<String>,
+    /// The album (music search only)
+    album: Option<String>,
+    /// The record label (music search only)
+    label: Option<String>,
+    /// The track (music search only)
+    track: Option<String>,
+    /// The author (book search only)
+    author: Option<String>,
+    /// The book title (book search only)
+    title: Option<String>,
+    /// The publisher (book search only)
+    publisher: Option<String>,
 }
 
 impl SearchForm {
+    /// Reads the search parameters from the request's query
+    ///
+    /// Invalid `extended`, `offset` and `limit` values are ignored, as if they weren't given
+    fn from_request(request: &ApiRequest) -> SearchForm {
+        let text = |name: &str| -> Option<String> { request.param(name).map(|v| v.to_string()) };
+
+        return SearchForm {
+            q: text("q"),
+            apikey: text("apikey"),
+            cat: text("cat"),
+            attrs: text("attrs"),
+            extended: request.param("extended").and_then(|v| v.parse().ok()),
+            offset: request.param("offset").and_then(|v| v.parse().ok()),
+            limit: request.param("limit").and_then(|v| v.parse().ok()),
+            season: text("season"),
+            ep: text("ep"),
+            tvdbid: text("tvdbid"),
+            rid: text("rid"),
+            tvmazeid: text("tvmazeid"),
+            imdbid: text("imdbid"),
+            tmdbid: text("tmdbid"),
+            traktid: text("traktid"),
+            year: text("year"),
+            genre: text("genre"),
+            artist: text("artist"),
+            album: text("album"),
+            label: text("label"),
+            track: text("track"),
+            author: text("author"),
+            title: text("title"),
+            publisher: text("publisher"),
+        };
+    }
+
     /// Converts it to a SearchParameters object
+    ///
+    /// Returns [`TorznabError::IncorrectParameter`] if any of the typed parameters are invalid
     fn to_parameters(
         &self,
         conf: impl Borrow<Config>,
         search_type: impl AsRef<str>,
-    ) -> SearchParameters {
+    ) -> Result<SearchParameters, TorznabError> {
         let search_type: &str = search_type.as_ref();
         let conf: Config = conf.borrow().clone();
 
@@ -56,11 +333,11 @@ impl SearchForm {
         let extended_attribute_names: Option<Vec<String>> = self
             .attrs
             .clone()
-            .and_then(|l| Some(l.split(",").map(|s| s.to_string()).collect()));
+            .map(|l| l.split(",").map(|s| s.to_string()).collect());
 
         // let mut extended_attrs: Option<bool> = self.extended.and_then(|k| if k == 1 { Some(true) } else { Some(false) }); // was this what you were trying to do?
         let mut extended_attrs = None;
-        if !self.extended.is_none() && self.extended.ok_or(false).unwrap() == 1 {
+        if self.extended == Some(1) {
             extended_attrs = Some(true);
         }
 
@@ -72,24 +349,204 @@ impl SearchForm {
             limit = 1
         }
 
-        return SearchParameters {
+        let mut tv = None;
+        let mut movie = None;
+        let mut music = None;
+        let mut book = None;
+        match search_type {
+            "tv-search" => {
+                tv = Some(self.to_tv_parameters()?);
+            }
+            "movie-search" => {
+                movie = Some(self.to_movie_parameters()?);
+            }
+            "audio-search" => {
+                music = Some(self.to_music_parameters()?);
+            }
+            "book-search" => {
+                book = Some(self.to_book_parameters());
+            }
+            _ => {}
+        }
+
+        return Ok(SearchParameters {
             search_type: search_type.to_string(),
             q: self.q.clone(),
             apikey: self.apikey.clone(),
-            categories: categories,
+            categories,
             attributes: extended_attribute_names,
-            extended_attrs: extended_attrs,
+            extended_attrs,
             offset: self.offset,
-            limit: limit,
+            limit,
+            principal: None,
+            tv,
+            movie,
+            music,
+            book,
+        });
+    }
+
+    /// Parses the TV-specific parameters
+    fn to_tv_parameters(&self) -> Result<TvSearchParameters, TorznabError> {
+        let season = parse_number(&self.season, "season")?;
+
+        let episode = match &self.ep {
+            Some(ep) if !ep.trim().is_empty() => Some(parse_episode(ep, season.is_some())?),
+            _ => None,
         };
+
+        return Ok(TvSearchParameters {
+            season,
+            episode,
+            tvdb_id: parse_number(&self.tvdbid, "tvdbid")?,
+            tvrage_id: parse_number(&self.rid, "rid")?,
+            tvmaze_id: parse_number(&self.tvmazeid, "tvmazeid")?,
+            imdb_id: non_empty(&self.imdbid),
+            tmdb_id: parse_number(&self.tmdbid, "tmdbid")?,
+            trakt_id: parse_number(&self.traktid, "traktid")?,
+        });
+    }
+
+    /// Parses the movie-specific parameters
+    fn to_movie_parameters(&self) -> Result<MovieSearchParameters, TorznabError> {
+        return Ok(MovieSearchParameters {
+            imdb_id: non_empty(&self.imdbid),
+            tmdb_id: parse_number(&self.tmdbid, "tmdbid")?,
+            year: parse_number(&self.year, "year")?,
+            genre: non_empty(&self.genre),
+        });
+    }
+
+    /// Parses the music-specific parameters
+    fn to_music_parameters(&self) -> Result<MusicSearchParameters, TorznabError> {
+        return Ok(MusicSearchParameters {
+            artist: non_empty(&self.artist),
+            album: non_empty(&self.album),
+            label: non_empty(&self.label),
+            track: non_empty(&self.track),
+            year: parse_number(&self.year, "year")?,
+        });
+    }
+
+    /// Parses the book-specific parameters
+    fn to_book_parameters(&self) -> BookSearchParameters {
+        return BookSearchParameters {
+            author: non_empty(&self.author),
+            title: non_empty(&self.title),
+            publisher: non_empty(&self.publisher),
+        };
+    }
+}
+
+/// Returns a copy of an optional text parameter, treating empty values as missing
+fn non_empty(value: &Option<String>) -> Option<String> {
+    return value.clone().filter(|value| !value.trim().is_empty());
+}
+
+/// Parses an optional numeric parameter, treating empty values as missing
+///
+/// `name` is the name of the parameter, for the error if it's invalid
+fn parse_number(value: &Option<String>, name: &str) -> Result<Option<u32>, TorznabError> {
+    match value {
+        Some(value) if !value.trim().is_empty() => {
+            return value
+                .trim()
+                .parse::<u32>()
+                .map(Some)
+                .map_err(|_| TorznabError::IncorrectParameter(name.to_string()));
+        }
+        _ => {
+            return Ok(None);
+        }
+    }
+}
+
+/// Parses the `ep` parameter of a TV search
+///
+/// `MM/DD` is a daily show's episode, and a plain number is either a regular episode number, or an absolute one if there's no season
+fn parse_episode(ep: &str, has_season: bool) -> Result<Episode, TorznabError> {
+    let error = || TorznabError::IncorrectParameter("ep".to_string());
+    let ep = ep.trim();
+
+    if let Some((month, day)) = ep.split_once('/') {
+        let month = month.parse::<u8>().map_err(|_| error())?;
+        let day = day.parse::<u8>().map_err(|_| error())?;
+        if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+            return Err(error());
+        }
+        return Ok(Episode::Daily { month, day });
+    }
+
+    let number = ep.parse::<u32>().map_err(|_| error())?;
+    if has_season {
+        return Ok(Episode::Number(number));
+    }
+    return Ok(Episode::Absolute(number));
+}
+
+/// Runs the configured [`Authenticator`] (if there is one) on a `search_type` request, returning the principal for [`SearchParameters::principal`]
+///
+/// Returns an error if the request isn't authorized
+async fn authenticate(
+    conf: &Config,
+    search_type: &str,
+    request: &ApiRequest,
+) -> Result<Option<Principal>, TorznabError> {
+    let auth = match &conf.auth {
+        Some(auth) => auth,
+        None => {
+            return Ok(None);
+        }
+    };
+
+    let context = RequestContext {
+        client_ip: request.client_ip,
+        headers: request.headers.clone(),
+        search_type: search_type.to_string(),
+    };
+
+    match auth
+        .authenticate(request.param("apikey").map(|v| v.to_string()), &context)
+        .await?
+    {
+        Some(principal) => {
+            return Ok(Some(principal));
+        }
+        None => {
+            return Err(TorznabError::IncorrectCredentials);
+        }
     }
 }
 
-/// Capabilities API endpoint (`/api?t=caps`)
+/// Creates a Torznab error response (`<error code="..." description="..."/>`) for `error`
+pub(crate) fn error_response(error: TorznabError) -> ApiResponse {
+    let buffer = Vec::new();
+    let mut writer = EmitterConfig::new().create_writer(buffer);
+
+    writer
+        .write(
+            XmlEvent::start_element("error")
+                .attr("code", error.code().to_string().as_str())
+                .attr("description", error.description().as_str()),
+        )
+        .unwrap();
+    writer.write(XmlEvent::end_element()).unwrap(); // close `error`
+
+    let result = str::from_utf8(writer.into_inner().as_slice())
+        .unwrap()
+        .to_string(); // Convert buffer to a String
+
+    return ApiResponse {
+        status: error.http_status(),
+        content_type: CONTENT_TYPE,
+        body: result,
+    };
+}
+
+/// Capabilities API function (`t=caps`)
 ///
 /// Note that an apikey is *not* required for this function, regardless of whether it's required for the rest.
-#[get("/api?t=caps", rank = 1)]
-pub(crate) async fn caps(conf: &State<Config>) -> status::Custom<RawXml<String>> {
+fn caps(conf: &Config) -> ApiResponse {
     let buffer = Vec::new();
     let mut writer = EmitterConfig::new().create_writer(buffer);
 
@@ -97,15 +554,12 @@ pub(crate) async fn caps(conf: &State<Config>) -> status::Custom<RawXml<String>>
 
     // add the server info
     let mut element = XmlEvent::start_element("server");
-    match &conf.caps.server_info {
-        Some(server_info) => {
-            // needs to be a vec since if i just `.as_str()` them, they don't live long enough
-            let server_info_vec: Vec<(&String, &String)> = server_info.iter().collect();
-            for (key, value) in server_info_vec {
-                element = element.attr(key.as_str(), value);
-            }
+    if let Some(server_info) = &conf.caps.server_info {
+        // needs to be a vec since if i just `.as_str()` them, they don't live long enough
+        let server_info_vec: Vec<(&String, &String)> = server_info.iter().collect();
+        for (key, value) in server_info_vec {
+            element = element.attr(key.as_str(), value);
         }
-        None => {}
     }
     writer.write(element).unwrap();
     writer.write(XmlEvent::end_element()).unwrap(); // close `server`
@@ -161,42 +615,37 @@ pub(crate) async fn caps(conf: &State<Config>) -> status::Custom<RawXml<String>>
     }
     writer.write(XmlEvent::end_element()).unwrap(); // close `categories`
 
-    match &conf.caps.genres {
-        Some(genres) => {
-            writer.write(XmlEvent::start_element("genres")).unwrap();
-
-            for genre in genres {
-                writer
-                    .write(
-                        XmlEvent::start_element("genre")
-                            .attr("id", genre.id.to_string().as_str())
-                            .attr("categoryid", genre.category_id.to_string().as_str())
-                            .attr("name", genre.name.as_str()),
-                    )
-                    .unwrap();
-                writer.write(XmlEvent::end_element()).unwrap(); // close `genre` element
-            }
-            writer.write(XmlEvent::end_element()).unwrap(); // close `genres` element
+    if let Some(genres) = &conf.caps.genres {
+        writer.write(XmlEvent::start_element("genres")).unwrap();
+
+        for genre in genres {
+            writer
+                .write(
+                    XmlEvent::start_element("genre")
+                        .attr("id", genre.id.to_string().as_str())
+                        .attr("categoryid", genre.category_id.to_string().as_str())
+                        .attr("name", genre.name.as_str()),
+                )
+                .unwrap();
+            writer.write(XmlEvent::end_element()).unwrap(); // close `genre` element
         }
-        None => {}
+        writer.write(XmlEvent::end_element()).unwrap(); // close `genres` element
     }
 
-    match &conf.caps.tags {
-        Some(tags) => {
-            writer.write(XmlEvent::start_element("tags")).unwrap();
+    if let Some(tags) = &conf.caps.tags {
+        writer.write(XmlEvent::start_element("tags")).unwrap();
 
-            for tag in tags {
-                writer
-                    .write(
-                        XmlEvent::start_element("tag")
-                            .attr("name", tag.name.as_str())
-                            .attr("description", tag.description.as_str()),
-                    )
-                    .unwrap();
-            }
-            writer.write(XmlEvent::end_element()).unwrap(); // close `tags` element
+        for tag in tags {
+            writer
+                .write(
+                    XmlEvent::start_element("tag")
+                        .attr("name", tag.name.as_str())
+                        .attr("description", tag.description.as_str()),
+                )
+                .unwrap();
+            writer.write(XmlEvent::end_element()).unwrap(); // close `tag` element
         }
-        None => {}
+        writer.write(XmlEvent::end_element()).unwrap(); // close `tags` element
     }
 
     writer.write(XmlEvent::end_element()).unwrap(); // close `caps`
@@ -204,183 +653,76 @@ pub(crate) async fn caps(conf: &State<Config>) -> status::Custom<RawXml<String>>
         .unwrap()
         .to_string(); // Convert buffer to a String
 
-    return status::Custom(Status::Ok, RawXml(result));
+    return ApiResponse::ok(result);
 }
 
-#[get("/api?t=search&<form..>", rank = 2)]
-/// The general search function
-pub(crate) async fn search(
-    conf: &State<Config>,
-    form: SearchForm,
-) -> status::Custom<RawXml<String>> {
-    // oh god this is horrible but it works
-    let parameters = form.to_parameters((**conf).clone(), "search");
-
-    let mut unauthorized = false;
-    match conf.auth {
-        Some(auth) => {
-            match parameters.apikey.clone() {
-                Some(apikey) => {
-                    if !auth(apikey).unwrap() {
-                        unauthorized = true;
-                    }
-                }
-                None => {
-                    unauthorized = true;
-                }
-            }
-            // that unwrap_or_else is to return "" if the apikey isn't specified
-        }
-        None => {}
-    }
-
-    if unauthorized {
-        return status::Custom(Status::Unauthorized, RawXml("401 Unauthorized".to_string()));
-    }
+/// The search functions (`t=search`, `tvsearch`, etc.), once the parameters are parsed and the request is authenticated
+async fn search_handler(conf: &Config, parameters: SearchParameters) -> ApiResponse {
+    let offset = parameters.offset.unwrap_or(0);
+    let limit = parameters.limit;
+    let attribute_filter = AttributeFilter::new(&parameters);
 
-    return search_handler(conf, parameters).await;
-}
-
-#[get("/api?t=tvsearch&<form..>", rank = 3)]
-/// The TV search function
-pub(crate) async fn tv_search(
-    conf: &State<Config>,
-    form: SearchForm,
-) -> status::Custom<RawXml<String>> {
-    // oh god this is horrible but it works
-    let parameters = form.to_parameters((**conf).clone(), "tv-search");
-
-    let mut unauthorized = false;
-    match conf.auth {
-        Some(auth) => {
-            match parameters.clone().apikey {
-                Some(apikey) => {
-                    if !auth(apikey).unwrap() {
-                        unauthorized = true;
-                    }
-                }
-                None => {
-                    unauthorized = true;
-                }
+    // only filter if asked to, and if any categories were actually requested
+    let mut category_filter = None;
+    if conf.filter_categories {
+        if let Some(ref categories) = parameters.categories {
+            if !categories.is_empty() {
+                category_filter = Some(conf.caps.expand_category_ids(categories));
             }
-            // that unwrap_or_else is to return "" if the apikey isn't specified
         }
-        None => {}
-    }
-
-    if unauthorized {
-        return status::Custom(Status::Unauthorized, RawXml("401 Unauthorized".to_string()));
     }
-
-    return search_handler(conf, parameters).await;
-}
-
-#[get("/api?t=movie&<form..>", rank = 4)]
-/// The movie search function
-pub(crate) async fn movie_search(
-    conf: &State<Config>,
-    form: SearchForm,
-) -> status::Custom<RawXml<String>> {
-    // oh god this is horrible but it works
-    let parameters = form.to_parameters((**conf).clone(), "movie-search");
-
-    let mut unauthorized = false;
-    match conf.auth {
-        Some(auth) => {
-            match parameters.clone().apikey {
-                Some(apikey) => {
-                    if !auth(apikey).unwrap() {
-                        unauthorized = true;
-                    }
-                }
-                None => {
-                    unauthorized = true;
-                }
-            }
-            // that unwrap_or_else is to return "" if the apikey isn't specified
+    let in_categories = |torrent: &Torrent| -> bool {
+        match &category_filter {
+            Some(ids) => torrent.category_ids.iter().any(|id| ids.contains(id)),
+            None => true,
         }
-        None => {}
-    }
+    };
 
-    if unauthorized {
-        return status::Custom(Status::Unauthorized, RawXml("401 Unauthorized".to_string()));
-    }
-
-    return search_handler(conf, parameters).await;
-}
-
-#[get("/api?t=music&<form..>", rank = 5)]
-/// The music search function
-pub(crate) async fn music_search(
-    conf: &State<Config>,
-    form: SearchForm,
-) -> status::Custom<RawXml<String>> {
-    // oh god this is horrible but it works
-    let parameters = form.to_parameters((**conf).clone(), "audio-search");
-
-    let mut unauthorized = false;
-    match conf.auth {
-        Some(auth) => {
-            match parameters.clone().apikey {
-                Some(apikey) => {
-                    if !auth(apikey).unwrap() {
-                        unauthorized = true;
-                    }
-                }
-                None => {
-                    unauthorized = true;
-                }
-            }
-            // that unwrap_or_else is to return "" if the apikey isn't specified
+    let results = match conf.search.search(parameters).await {
+        Ok(results) => results,
+        Err(error) => {
+            return error_response(error);
         }
-        None => {}
-    }
-
-    if unauthorized {
-        return status::Custom(Status::Unauthorized, RawXml("401 Unauthorized".to_string()));
-    }
+    };
 
-    return search_handler(conf, parameters).await;
-}
-
-#[get("/api?t=book&<form..>", rank = 6)]
-/// The music search function
-pub(crate) async fn book_search(
-    conf: &State<Config>,
-    form: SearchForm,
-) -> status::Custom<RawXml<String>> {
-    // oh god this is horrible but it works
-    let parameters = form.to_parameters((**conf).clone(), "book-search");
-
-    let mut unauthorized = false;
-    match conf.auth {
-        Some(auth) => {
-            match parameters.clone().apikey {
-                Some(apikey) => {
-                    if !auth(apikey).unwrap() {
-                        unauthorized = true;
-                    }
-                }
-                None => {
-                    unauthorized = true;
-                }
-            }
-            // that unwrap_or_else is to return "" if the apikey isn't specified
+    let (torrents, total): (Vec<Torrent>, u64) = match results {
+        SearchResults::All(torrents) => {
+            let torrents: Vec<Torrent> = torrents.into_iter().filter(in_categories).collect();
+            let total = torrents.len() as u64;
+            let page = torrents
+                .into_iter()
+                .skip(offset as usize)
+                .take(limit as usize)
+                .collect();
+            (page, total)
         }
-        None => {}
-    }
+        SearchResults::Paged { torrents, total } => {
+            // just in case the backend returned more than it was asked for
+            let page = torrents
+                .into_iter()
+                .filter(in_categories)
+                .take(limit as usize)
+                .collect();
+            (page, total)
+        }
+    };
 
-    if unauthorized {
-        return status::Custom(Status::Unauthorized, RawXml("401 Unauthorized".to_string()));
+    // checked before writing anything, so that a bad torrent results in an error instead of a partial feed
+    for torrent in &torrents {
+        if torrent
+            .torrent_file_url
+            .clone()
+            .unwrap_or_default()
+            .is_empty()
+            && torrent.magnet_uri.clone().unwrap_or_default().is_empty()
+        {
+            return error_response(TorznabError::Unknown(format!(
+                "Torrent \"{}\" contains neither a .torrent file URL nor a magnet URI",
+                torrent.title
+            )));
+        }
     }
 
-    return search_handler(conf, parameters).await;
-}
-
-async fn search_handler(
-    conf: &State<Config>,
-    parameters: SearchParameters,
-) -> status::Custom<RawXml<String>> {
     let buffer = Vec::new();
     let mut writer = EmitterConfig::new().create_writer(buffer);
     writer
@@ -388,7 +730,11 @@ async fn search_handler(
             XmlEvent::start_element("rss")
                 .attr("version", "1.0")
                 .attr("xmlns:atom", "http://www.w3.org/2005/Atom")
-                .attr("xmlns:torznab", "http://torznab.com/schemas/2015/feed"),
+                .attr("xmlns:torznab", "http://torznab.com/schemas/2015/feed")
+                .attr(
+                    "xmlns:newznab",
+                    "http://www.newznab.com/DTD/2010/feeds/attributes/",
+                ),
         )
         .unwrap();
     writer.write(XmlEvent::start_element("channel")).unwrap();
@@ -399,23 +745,16 @@ async fn search_handler(
                 .attr("type", "application/rss+xml"),
         )
         .unwrap();
+    writer.write(XmlEvent::end_element()).unwrap(); // close `atom:link`
 
     // add `title`
     writer.write(XmlEvent::start_element("title")).unwrap();
     let mut title_provided = false;
-    match &conf.caps.server_info {
-        Some(server_info) => {
-            if server_info.contains_key("title") {
-                match server_info.get("title") {
-                    Some(title) => {
-                        writer.write(XmlEvent::characters(title)).unwrap();
-                        title_provided = true;
-                    }
-                    None => {}
-                }
-            }
+    if let Some(server_info) = &conf.caps.server_info {
+        if let Some(title) = server_info.get("title") {
+            writer.write(XmlEvent::characters(title)).unwrap();
+            title_provided = true;
         }
-        None => {}
     }
     if !title_provided {
         writer
@@ -424,15 +763,19 @@ async fn search_handler(
     }
     writer.write(XmlEvent::end_element()).unwrap();
 
-    for item in (conf.search)(parameters).unwrap() {
-        let torrent_file_url = item.torrent_file_url.clone().unwrap_or_default();
+    // add the paging info
+    writer
+        .write(
+            XmlEvent::start_element("newznab:response")
+                .attr("offset", offset.to_string().as_str())
+                .attr("total", total.to_string().as_str()),
+        )
+        .unwrap();
+    writer.write(XmlEvent::end_element()).unwrap(); // close `newznab:response`
 
+    for item in torrents {
         let magnet_uri = item.magnet_uri.clone().unwrap_or_default();
 
-        if torrent_file_url == "" && magnet_uri == "" {
-            panic!("Torrent contains neither a .torrent file URL, not a magnet URI")
-        }
-
         // start `item`
         writer.write(XmlEvent::start_element("item")).unwrap();
 
@@ -445,100 +788,761 @@ async fn search_handler(
         writer
             .write(XmlEvent::start_element("description"))
             .unwrap();
-        if !item.description.is_none() {
-            writer
-                .write(XmlEvent::characters(&item.description.unwrap_or_default()))
-                .unwrap();
+        if let Some(description) = &item.description {
+            writer.write(XmlEvent::characters(description)).unwrap();
         }
         writer.write(XmlEvent::end_element()).unwrap();
 
-        // add `size` (torznab attr)
-        writer
-            .write(
-                XmlEvent::start_element("torznab:attr")
-                    .attr("size", item.size.to_string().as_str()),
-            )
-            .unwrap();
+        // add `link`; falls back to `torrent_file_url`, then `magnet_uri`
+        let link = item
+            .link
+            .clone()
+            .or(item.torrent_file_url.clone())
+            .unwrap_or(magnet_uri.clone());
+        writer.write(XmlEvent::start_element("link")).unwrap();
+        writer.write(XmlEvent::characters(&link)).unwrap();
         writer.write(XmlEvent::end_element()).unwrap();
 
-        // add `category`s (torznab attr)
-        for id in item.category_ids {
-            writer
-                .write(
-                    XmlEvent::start_element("torznab:attr")
-                        .attr("name", "category")
-                        .attr("value", id.to_string().as_str()),
-                )
-                .unwrap();
+        // add `pubDate`
+        if let Some(pub_date) = &item.pub_date {
+            writer.write(XmlEvent::start_element("pubDate")).unwrap();
+            writer.write(XmlEvent::characters(pub_date)).unwrap();
             writer.write(XmlEvent::end_element()).unwrap();
         }
 
-        // add `link` and `enclosure` (for torrent/magnet uri)
-        // first check if `link` exists in hashmap, and if not, fallback to `torrent_file_url`, then `magnet_uri`
-        writer.write(XmlEvent::start_element("link")).unwrap();
-        let mut link_filled = false; // nesting two layers down of matches, so this is to keep track rather than just doing it in the None
-        match item.other_attributes {
-            Some(ref attributes) => match attributes.get("link") {
-                Some(tmp) => {
-                    writer.write(XmlEvent::characters(tmp)).unwrap();
-                    link_filled = true;
-                }
-                None => {}
-            },
-            None => {}
-        }
-
-        if !link_filled {
-            match item.torrent_file_url {
-                Some(ref url) => {
-                    writer.write(XmlEvent::characters(&url)).unwrap();
-                    writer.write(XmlEvent::end_element()).unwrap();
-                    writer
-                        .write(
-                            XmlEvent::start_element("enclosure")
-                                .attr("url", &url)
-                                .attr("length", 0.to_string().as_str())
-                                .attr("type", "application/x-bittorrent"),
-                        )
-                        .unwrap();
-                    writer.write(XmlEvent::end_element()).unwrap();
-                }
-                None => {
-                    writer.write(XmlEvent::characters(&magnet_uri)).unwrap();
-                    writer.write(XmlEvent::end_element()).unwrap();
-                    writer
-                        .write(
-                            XmlEvent::start_element("enclosure")
-                                .attr("url", &magnet_uri)
-                                .attr("length", 0.to_string().as_str())
-                                .attr("type", "application/x-bittorrent;x-scheme-handler/magnet"),
-                        )
-                        .unwrap();
-                    writer.write(XmlEvent::end_element()).unwrap();
-                }
-            }
+        // add `enclosure`, for the .torrent URL if there is one, otherwise the magnet URI
+        let enclosure = match item.torrent_file_url {
+            Some(ref url) => XmlEvent::start_element("enclosure")
+                .attr("url", url)
+                .attr("length", "0")
+                .attr("type", "application/x-bittorrent"),
+            None => XmlEvent::start_element("enclosure")
+                .attr("url", &magnet_uri)
+                .attr("length", "0")
+                .attr("type", "application/x-bittorrent;x-scheme-handler/magnet"),
+        };
+        writer.write(enclosure).unwrap();
+        writer.write(XmlEvent::end_element()).unwrap();
+
+        // add `size` and `category`s (torznab attrs)
+        write_attr(&mut writer, "size", &item.size.to_string());
+        for id in &item.category_ids {
+            write_attr(&mut writer, "category", &id.to_string());
         }
 
-        // add the remaining `other_attributes`
-        match item.other_attributes {
-            Some(ref other_attributes) => {
-                for (key, value) in other_attributes {
-                    writer
-                        .write(XmlEvent::start_element("torznab::attr").attr(key.as_str(), value))
-                        .unwrap();
+        // add the extended attributes that were asked for: `magneturl`, the predefined attributes, then the remaining `other_attributes`
+        if !magnet_uri.is_empty() && attribute_filter.includes("magneturl") {
+            write_attr(&mut writer, "magneturl", &magnet_uri);
+        }
+        for (name, value) in item.attributes.to_pairs() {
+            if attribute_filter.includes(name) {
+                write_attr(&mut writer, name, &value);
+            }
+        }
+        if let Some(ref other_attributes) = item.other_attributes {
+            for (name, value) in other_attributes {
+                if attribute_filter.includes(name) {
+                    write_attr(&mut writer, name, value);
                 }
             }
-            None => {}
         }
 
         writer.write(XmlEvent::end_element()).unwrap();
     }
-    writer.write(XmlEvent::end_element()).unwrap(); // close `title`
     writer.write(XmlEvent::end_element()).unwrap(); // close `channel`
     writer.write(XmlEvent::end_element()).unwrap(); // close `rss`
     let result = str::from_utf8(writer.into_inner().as_slice())
         .unwrap()
         .to_string(); // Convert buffer to a String
 
-    return status::Custom(Status::Ok, RawXml(result));
+    return ApiResponse::ok(result);
+}
+
+/// Decides which extended attributes (i.e. everything but `size` and `category`) are listed for each item
+///
+/// By default none are, `attrs` lists specific ones, and `extended=1` lists all of them
+struct AttributeFilter {
+    /// Whether all extended attributes should be listed (`extended=1`)
+    all: bool,
+    /// The names of the extended attributes to list (`attrs`)
+    names: Vec<String>,
+}
+
+impl AttributeFilter {
+    fn new(parameters: &SearchParameters) -> AttributeFilter {
+        return AttributeFilter {
+            all: parameters.extended_attrs == Some(true),
+            names: parameters.attributes.clone().unwrap_or_default(),
+        };
+    }
+
+    /// Returns whether the extended attribute `name` should be listed
+    fn includes(&self, name: &str) -> bool {
+        return self.all || self.names.iter().any(|n| n.trim() == name);
+    }
+}
+
+/// Writes a `<torznab:attr name="..." value="..."/>` element
+fn write_attr<W: std::io::Write>(writer: &mut xml::EventWriter<W>, name: &str, value: &str) {
+    writer
+        .write(
+            XmlEvent::start_element("torznab:attr")
+                .attr("name", name)
+                .attr("value", value),
+        )
+        .unwrap();
+    writer.write(XmlEvent::end_element()).unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::dummy::create_empty_config;
+    use async_trait::async_trait;
+    use std::net::Ipv4Addr;
+    use std::sync::{Arc, Mutex};
+
+    /// A search backend that records the parameters of each search, and returns `results`
+    struct Recorder {
+        searches: Mutex<Vec<SearchParameters>>,
+        results: SearchResults,
+    }
+
+    impl Recorder {
+        fn new(results: SearchResults) -> Arc<Recorder> {
+            return Arc::new(Recorder {
+                searches: Mutex::new(Vec::new()),
+                results,
+            });
+        }
+
+        /// Returns the parameters of the last search
+        fn last(&self) -> SearchParameters {
+            return self.searches.lock().unwrap().last().unwrap().clone();
+        }
+    }
+
+    #[async_trait]
+    impl SearchBackend for Arc<Recorder> {
+        async fn search(
+            &self,
+            parameters: SearchParameters,
+        ) -> Result<SearchResults, TorznabError> {
+            self.searches.lock().unwrap().push(parameters);
+            return Ok(self.results.clone());
+        }
+    }
+
+    /// Lets `letmein` in as `someone`, suspends `suspended`, and records the context of each request
+    struct Keys {
+        contexts: Mutex<Vec<RequestContext>>,
+    }
+
+    #[async_trait]
+    impl Authenticator for Arc<Keys> {
+        async fn authenticate(
+            &self,
+            apikey: Option<String>,
+            context: &RequestContext,
+        ) -> Result<Option<Principal>, TorznabError> {
+            self.contexts.lock().unwrap().push(context.clone());
+            match apikey.as_deref() {
+                Some("letmein") => {
+                    let mut principal = Principal::new("someone");
+                    principal
+                        .attributes
+                        .insert("plan".to_string(), "free".to_string());
+                    return Ok(Some(principal));
+                }
+                Some("suspended") => {
+                    return Err(TorznabError::AccountSuspended);
+                }
+                _ => {
+                    return Ok(None);
+                }
+            }
+        }
+    }
+
+    /// Caps with every search type available
+    fn all_searches_caps() -> Caps {
+        return Caps::builder()
+            .limits(100, 20)
+            .search_info(
+                SearchInfo::builder("search")
+                    .supported_param("q")
+                    .build()
+                    .unwrap(),
+            )
+            .search_info(
+                SearchInfo::builder("tv-search")
+                    .supported_params(["q", "season", "ep"])
+                    .build()
+                    .unwrap(),
+            )
+            .search_info(
+                SearchInfo::builder("movie-search")
+                    .supported_params(["q", "imdbid", "tmdbid", "year", "genre"])
+                    .build()
+                    .unwrap(),
+            )
+            .search_info(
+                SearchInfo::builder("audio-search")
+                    .supported_params(["q", "artist", "album", "label", "track", "year"])
+                    .build()
+                    .unwrap(),
+            )
+            .search_info(
+                SearchInfo::builder("book-search")
+                    .supported_params(["q", "author", "title", "publisher"])
+                    .build()
+                    .unwrap(),
+            )
+            .category(
+                Category::builder(5000, "TV")
+                    .subcategory(5040, "TV/HD")
+                    .build()
+                    .unwrap(),
+            )
+            .build()
+            .unwrap();
+    }
+
+    /// Returns the Torznab error code of a response, or `None` if it isn't an error
+    fn error_code(response: &ApiResponse) -> Option<u32> {
+        let start = response.body.find(r#"<error code=""#)? + r#"<error code=""#.len();
+        let end = start + response.body[start..].find('"')?;
+        return response.body[start..end].parse().ok();
+    }
+
+    /// Handles a request with the raw query `query`, returning the Torznab error code of the response, if any
+    async fn query_error_code(conf: &Config, query: &str) -> Option<u32> {
+        return error_code(&handle(conf, ApiRequest::from_query_string(query)).await);
+    }
+
+    #[test]
+    fn error_response_is_torznab_error_xml() {
+        let response = error_response(TorznabError::IncorrectParameter("limit".to_string()));
+        assert_eq!(response.status, 400);
+        assert_eq!(response.content_type, CONTENT_TYPE);
+        assert!(response
+            .body
+            .contains(r#"<error code="201" description="Incorrect parameter: limit" />"#));
+    }
+
+    #[test]
+    fn query_string_is_decoded() {
+        let request = ApiRequest::from_query_string(
+            "?t=search&q=some+thing%2Fother&&cat=2000,5000&q=ignored&empty",
+        );
+        assert_eq!(request.param("t"), Some("search"));
+        assert_eq!(request.param("q"), Some("some thing/other"));
+        assert_eq!(request.param("cat"), Some("2000,5000"));
+        assert_eq!(request.param("empty"), Some(""));
+        assert_eq!(request.param("apikey"), None);
+    }
+
+    #[actix_rt::test]
+    async fn handle_dispatches_on_function() {
+        let conf = create_empty_config();
+
+        let response = handle(&conf, ApiRequest::from_query_string("t=caps")).await;
+        assert_eq!(response.status, 200);
+        assert!(response.body.contains("<caps>"));
+        assert!(response
+            .body
+            .ends_with(r#"<tags><tag name="a" description="b" /></tags></caps>"#));
+
+        let response = handle(
+            &conf,
+            ApiRequest::from_query_string("t=search&q=normal&apikey=letmein"),
+        )
+        .await;
+        assert_eq!(response.status, 200);
+        assert!(response
+            .body
+            .contains("<title>totally normal torrent</title>"));
+
+        let response = handle(&conf, ApiRequest::from_query_string("t=nope")).await;
+        assert_eq!(response.status, 400);
+        assert!(response.body.contains(r#"code="202""#));
+
+        let response = handle(&conf, ApiRequest::from_query_string("q=normal")).await;
+        assert!(response
+            .body
+            .contains(r#"<error code="200" description="Missing parameter: t" />"#));
+    }
+
+    #[actix_rt::test]
+    async fn handle_rejects_unavailable_searches() {
+        let mut conf = create_empty_config();
+        conf.caps.searching.push(SearchInfo {
+            search_type: "tv-search".to_string(),
+            available: false,
+            supported_params: vec!["q".to_string()],
+        });
+
+        // not listed
+        let response = handle(
+            &conf,
+            ApiRequest::from_query_string("t=movie&q=normal&apikey=letmein"),
+        )
+        .await;
+        assert_eq!(response.status, 400);
+        assert!(response
+            .body
+            .contains(r#"<error code="203" description="Function not available" />"#));
+
+        // listed, but not available
+        let response = handle(
+            &conf,
+            ApiRequest::from_query_string("t=tvsearch&q=normal&apikey=letmein"),
+        )
+        .await;
+        assert!(response.body.contains(r#"code="203""#));
+    }
+
+    #[actix_rt::test]
+    async fn unsupported_params_are_stripped_or_rejected() {
+        let caps = Caps::builder()
+            .limits(100, 20)
+            .search_info(
+                SearchInfo::builder("tv-search")
+                    .supported_params(["q", "season"])
+                    .build()
+                    .unwrap(),
+            )
+            .category(Category::builder(5000, "TV").build().unwrap())
+            .build()
+            .unwrap();
+        // errors out if it gets an unsupported parameter
+        let backend = |parameters: SearchParameters| -> Result<Vec<Torrent>, String> {
+            if parameters.tv.unwrap().tvdb_id.is_some() {
+                return Err("got tvdbid".to_string());
+            }
+            return Ok(vec![]);
+        };
+        let query = "t=tvsearch&q=normal&season=1&tvdbid=1234&ep=";
+
+        let conf = Config::builder(caps.clone())
+            .search(backend)
+            .unsupported_params(UnsupportedParams::Strip)
+            .build()
+            .unwrap();
+        let response = handle(&conf, ApiRequest::from_query_string(query)).await;
+        assert_eq!(response.status, 200);
+
+        let conf = Config::builder(caps)
+            .search(backend)
+            .unsupported_params(UnsupportedParams::Reject)
+            .build()
+            .unwrap();
+        let response = handle(&conf, ApiRequest::from_query_string(query)).await;
+        assert!(response
+            .body
+            .contains(r#"<error code="201" description="Incorrect parameter: tvdbid" />"#));
+    }
+
+    #[actix_rt::test]
+    async fn unauthorized_requests_are_rejected_before_parsing() {
+        let keys = Arc::new(Keys {
+            contexts: Mutex::new(Vec::new()),
+        });
+        let conf = Config::builder(all_searches_caps())
+            .search(Recorder::new(SearchResults::All(vec![])))
+            .auth(keys)
+            .unsupported_params(UnsupportedParams::Reject)
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            query_error_code(&conf, "t=search&q=normal").await,
+            Some(100)
+        );
+        assert_eq!(
+            query_error_code(&conf, "t=search&q=normal&apikey=wrong").await,
+            Some(100)
+        );
+        assert_eq!(
+            query_error_code(&conf, "t=search&q=normal&apikey=suspended").await,
+            Some(101)
+        );
+        assert_eq!(
+            query_error_code(&conf, "t=search&q=normal&apikey=letmein").await,
+            None
+        );
+
+        // bad or unsupported parameters only matter once the request is authorized
+        for query in [
+            "t=search&tvdbid=1",
+            "t=tvsearch&ep=pilot",
+            "t=movie&year=abc",
+        ] {
+            assert_eq!(query_error_code(&conf, query).await, Some(100), "{}", query);
+        }
+        assert_eq!(
+            query_error_code(&conf, "t=search&tvdbid=1&apikey=letmein").await,
+            Some(201)
+        );
+        assert_eq!(
+            query_error_code(&conf, "t=tvsearch&ep=pilot&apikey=letmein").await,
+            Some(201)
+        );
+        assert_eq!(
+            query_error_code(&conf, "t=movie&year=abc&apikey=letmein").await,
+            Some(201)
+        );
+    }
+
+    #[actix_rt::test]
+    async fn auth_function_errors_are_rejected() {
+        let conf = Config::builder(all_searches_caps())
+            .search(Recorder::new(SearchResults::All(vec![])))
+            .auth(|_apikey: String| -> Result<bool, String> {
+                Err("key store is down".to_string())
+            })
+            .build()
+            .unwrap();
+        let response = handle(
+            &conf,
+            ApiRequest::from_query_string("t=search&apikey=letmein"),
+        )
+        .await;
+        assert_eq!(error_code(&response), Some(900));
+        assert!(response.body.contains("key store is down"));
+    }
+
+    #[actix_rt::test]
+    async fn principal_and_request_context_are_passed_on() {
+        let keys = Arc::new(Keys {
+            contexts: Mutex::new(Vec::new()),
+        });
+        let backend = Recorder::new(SearchResults::All(vec![]));
+        let conf = Config::builder(all_searches_caps())
+            .search(backend.clone())
+            .auth(keys.clone())
+            .build()
+            .unwrap();
+
+        let headers = vec![
+            ("User-Agent".to_string(), "Sonarr".to_string()),
+            ("X-Forwarded-For".to_string(), "10.0.0.1".to_string()),
+        ];
+        let request = ApiRequest {
+            headers: headers.clone(),
+            client_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            ..ApiRequest::from_query_string("t=tvsearch&q=normal&apikey=letmein")
+        };
+        let response = handle(&conf, request).await;
+        assert_eq!(response.status, 200);
+
+        let context = keys.contexts.lock().unwrap()[0].clone();
+        assert_eq!(
+            context,
+            RequestContext {
+                client_ip: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+                headers,
+                search_type: "tv-search".to_string(),
+            }
+        );
+        assert_eq!(context.header("user-agent"), Some("Sonarr"));
+
+        let principal = backend.last().principal.unwrap();
+        assert_eq!(principal.id, "someone");
+        assert_eq!(principal.attributes["plan"], "free");
+    }
+
+    #[actix_rt::test]
+    async fn parse_movie_music_and_book_parameters() {
+        let backend = Recorder::new(SearchResults::All(vec![]));
+        let conf = Config::builder(all_searches_caps())
+            .search(backend.clone())
+            .build()
+            .unwrap();
+
+        let query = "t=movie&q=dune&imdbid=tt0087182&tmdbid=841&year=1984&genre=Sci-Fi";
+        assert_eq!(query_error_code(&conf, query).await, None);
+        let parameters = backend.last();
+        assert_eq!(parameters.search_type, "movie-search");
+        assert_eq!(parameters.q.as_deref(), Some("dune"));
+        assert_eq!(
+            parameters.movie,
+            Some(MovieSearchParameters {
+                imdb_id: Some("tt0087182".to_string()),
+                tmdb_id: Some(841),
+                year: Some(1984),
+                genre: Some("Sci-Fi".to_string()),
+            })
+        );
+        assert_eq!(
+            (parameters.tv, parameters.music, parameters.book),
+            (None, None, None)
+        );
+
+        let query = "t=music&artist=Someone&album=Something&label=&track=Intro&year=2001";
+        assert_eq!(query_error_code(&conf, query).await, None);
+        let parameters = backend.last();
+        assert_eq!(parameters.search_type, "audio-search");
+        assert_eq!(
+            parameters.music,
+            Some(MusicSearchParameters {
+                artist: Some("Someone".to_string()),
+                album: Some("Something".to_string()),
+                label: None,
+                track: Some("Intro".to_string()),
+                year: Some(2001),
+            })
+        );
+
+        let query = "t=book&author=Frank+Herbert&title=Dune&publisher=Chilton";
+        assert_eq!(query_error_code(&conf, query).await, None);
+        let parameters = backend.last();
+        assert_eq!(parameters.search_type, "book-search");
+        assert_eq!(
+            parameters.book,
+            Some(BookSearchParameters {
+                author: Some("Frank Herbert".to_string()),
+                title: Some("Dune".to_string()),
+                publisher: Some("Chilton".to_string()),
+            })
+        );
+
+        // empty numbers are treated as missing, but invalid ones are rejected
+        assert_eq!(query_error_code(&conf, "t=movie&year=").await, None);
+        assert_eq!(backend.last().movie.unwrap().year, None);
+        for query in ["t=movie&year=abc", "t=movie&tmdbid=-1", "t=music&year=abc"] {
+            assert_eq!(query_error_code(&conf, query).await, Some(201), "{}", query);
+        }
+        let response = handle(&conf, ApiRequest::from_query_string("t=movie&year=abc")).await;
+        assert!(response
+            .body
+            .contains(r#"description="Incorrect parameter: year""#));
+    }
+
+    #[actix_rt::test]
+    async fn parse_tv_parameters() {
+        let backend = Recorder::new(SearchResults::All(vec![]));
+        let conf = Config::builder(all_searches_caps())
+            .search(backend.clone())
+            .build()
+            .unwrap();
+
+        let query = "t=tvsearch&q=show&season=2&ep=5&tvdbid=121361&rid=24493&tvmazeid=82&imdbid=tt0944947&tmdbid=1399&traktid=1390";
+        assert_eq!(query_error_code(&conf, query).await, None);
+        let parameters = backend.last();
+        assert_eq!(parameters.search_type, "tv-search");
+        assert_eq!(parameters.q.as_deref(), Some("show"));
+        assert_eq!(
+            parameters.tv,
+            Some(TvSearchParameters {
+                season: Some(2),
+                episode: Some(Episode::Number(5)),
+                tvdb_id: Some(121361),
+                tvrage_id: Some(24493),
+                tvmaze_id: Some(82),
+                imdb_id: Some("tt0944947".to_string()),
+                tmdb_id: Some(1399),
+                trakt_id: Some(1390),
+            })
+        );
+        assert_eq!(
+            (parameters.movie, parameters.music, parameters.book),
+            (None, None, None)
+        );
+
+        // `ep` without a `season` is an absolute episode number
+        assert_eq!(query_error_code(&conf, "t=tvsearch&ep=1042").await, None);
+        assert_eq!(
+            backend.last().tv,
+            Some(TvSearchParameters {
+                episode: Some(Episode::Absolute(1042)),
+                ..Default::default()
+            })
+        );
+
+        // daily shows use the year as the season and `MM/DD` as the episode
+        assert_eq!(
+            query_error_code(&conf, "t=tvsearch&season=2024&ep=03/15").await,
+            None
+        );
+        assert_eq!(
+            backend.last().tv,
+            Some(TvSearchParameters {
+                season: Some(2024),
+                episode: Some(Episode::Daily { month: 3, day: 15 }),
+                ..Default::default()
+            })
+        );
+
+        let response = handle(
+            &conf,
+            ApiRequest::from_query_string("t=tvsearch&tvdbid=abc"),
+        )
+        .await;
+        assert_eq!(error_code(&response), Some(201));
+        assert!(response
+            .body
+            .contains(r#"description="Incorrect parameter: tvdbid""#));
+    }
+
+    fn numbered_torrents(count: u32) -> Vec<Torrent> {
+        return (0..count)
+            .map(|i| {
+                Torrent::builder(format!("torrent {}", i), 1)
+                    .category(5000)
+                    .torrent_file_url(format!("http://localhost/{}.torrent", i))
+                    .build()
+                    .unwrap()
+            })
+            .collect();
+    }
+
+    /// Returns the titles of the items in a search response
+    fn titles(response: &ApiResponse) -> Vec<String> {
+        let feed = crate::feed::parse_feed(&response.body).unwrap();
+        return feed.torrents.into_iter().map(|t| t.title).collect();
+    }
+
+    #[actix_rt::test]
+    async fn all_results_are_paged() {
+        let backend = Recorder::new(SearchResults::All(numbered_torrents(10)));
+        let conf = Config::builder(all_searches_caps())
+            .search(backend.clone())
+            .build()
+            .unwrap();
+
+        let response = handle(
+            &conf,
+            ApiRequest::from_query_string("t=search&offset=2&limit=3"),
+        )
+        .await;
+        assert_eq!(titles(&response), ["torrent 2", "torrent 3", "torrent 4"]);
+        assert!(response
+            .body
+            .contains(r#"<newznab:response offset="2" total="10" />"#));
+        assert_eq!((backend.last().offset, backend.last().limit), (Some(2), 3));
+
+        // past the end
+        let response = handle(
+            &conf,
+            ApiRequest::from_query_string("t=search&offset=10&limit=3"),
+        )
+        .await;
+        assert!(titles(&response).is_empty());
+        assert!(response
+            .body
+            .contains(r#"<newznab:response offset="10" total="10" />"#));
+
+        // no offset, and the default limit
+        let response = handle(&conf, ApiRequest::from_query_string("t=search")).await;
+        assert_eq!(titles(&response).len(), 10);
+        assert!(response
+            .body
+            .contains(r#"<newznab:response offset="0" total="10" />"#));
+    }
+
+    #[actix_rt::test]
+    async fn paged_results_are_not_paged_again() {
+        let backend = Recorder::new(SearchResults::Paged {
+            torrents: numbered_torrents(3),
+            total: 1234,
+        });
+        let conf = Config::builder(all_searches_caps())
+            .search(backend.clone())
+            .build()
+            .unwrap();
+
+        // the backend already skipped the first 100, so the page is listed as-is
+        let response = handle(
+            &conf,
+            ApiRequest::from_query_string("t=search&offset=100&limit=3"),
+        )
+        .await;
+        assert_eq!(titles(&response), ["torrent 0", "torrent 1", "torrent 2"]);
+        assert!(response
+            .body
+            .contains(r#"<newznab:response offset="100" total="1234" />"#));
+
+        // but it's still cut down to the limit
+        let response = handle(
+            &conf,
+            ApiRequest::from_query_string("t=search&offset=100&limit=2"),
+        )
+        .await;
+        assert_eq!(titles(&response), ["torrent 0", "torrent 1"]);
+        assert!(response.body.contains(r#"total="1234""#));
+    }
+
+    /// A stateful backend, which counts the searches it's had
+    struct Counter {
+        searches: tokio::sync::Mutex<u32>,
+    }
+
+    #[async_trait]
+    impl SearchBackend for Counter {
+        async fn search(&self, _: SearchParameters) -> Result<SearchResults, TorznabError> {
+            let mut searches = self.searches.lock().await;
+            *searches += 1;
+            let torrent = Torrent::builder(format!("search number {}", searches), 1)
+                .category(5000)
+                .magnet_uri(format!("magnet:?xt=urn:btih:{}", searches))
+                .build()
+                .unwrap();
+            return Ok(vec![torrent].into());
+        }
+    }
+
+    #[actix_rt::test]
+    async fn stateful_backend() {
+        let conf = Config::builder(all_searches_caps())
+            .search(Counter {
+                searches: tokio::sync::Mutex::new(0),
+            })
+            .build()
+            .unwrap();
+
+        for expected in ["search number 1", "search number 2"] {
+            let response = handle(&conf, ApiRequest::from_query_string("t=search&q=x")).await;
+            assert_eq!(response.status, 200);
+            assert_eq!(titles(&response), [expected]);
+        }
+    }
+
+    #[actix_rt::test]
+    async fn search_function_errors_are_unknown_errors() {
+        let search = |_: SearchParameters| -> Result<Vec<Torrent>, String> {
+            return Err("database is down".to_string());
+        };
+        assert_eq!(
+            SearchBackend::search(&search, SearchParameters::new("search", 10)).await,
+            Err(TorznabError::Unknown("database is down".to_string()))
+        );
+
+        let conf = Config::builder(all_searches_caps())
+            .search(search)
+            .build()
+            .unwrap();
+        let response = handle(&conf, ApiRequest::from_query_string("t=search")).await;
+        assert_eq!(error_code(&response), Some(900));
+        assert!(response.body.contains("database is down"));
+    }
+
+    #[test]
+    fn parse_episode_forms() {
+        assert_eq!(parse_episode("5", true), Ok(Episode::Number(5)));
+        assert_eq!(parse_episode("1024", false), Ok(Episode::Absolute(1024)));
+        assert_eq!(
+            parse_episode("03/15", true),
+            Ok(Episode::Daily { month: 3, day: 15 })
+        );
+        assert_eq!(
+            parse_episode("13/01", true),
+            Err(TorznabError::IncorrectParameter("ep".to_string()))
+        );
+        assert_eq!(
+            parse_episode("pilot", true),
+            Err(TorznabError::IncorrectParameter("ep".to_string()))
+        );
+    }
 }
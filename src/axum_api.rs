@@ -0,0 +1,307 @@
+//! Serves the API with [axum](https://docs.rs/axum) instead of Rocket; requires the `axum` feature
+//!
+//! Like the Rocket routes, this is a thin adapter over [`api::handle`]. To only pull in axum, disable the default features:
+//! ```toml
+//! torznab-toolkit = { version = "...", default-features = false, features = ["axum"] }
+//! ```
+//!
+//! Example:
+//! ```no_run
+//! # async fn example(config: torznab_toolkit::data::Config) -> Result<(), torznab_toolkit::RunError> {
+//! // serves the API at `/api`, like `torznab_toolkit::run` does with Rocket
+//! torznab_toolkit::axum_api::run(config, "127.0.0.1:8000").await?;
+//! # Ok(())
+//! # }
+//! ```
+use crate::api::{self, ApiRequest};
+use crate::data::{Config, TorznabError};
+use crate::server::{ServerHandle, ServerOptions, DEFAULT_ADDRESS, DEFAULT_PORT};
+use crate::{normalize_base_path, RunError};
+use axum::extract::{ConnectInfo, Request, State};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{any, get};
+use axum::Router;
+use std::net::SocketAddr;
+
+/// Returns a router serving the Torznab API at `base_path` (e.g. `/api`, or `/torznab/api`)
+///
+/// It can be merged into your own router (with [`Router::merge`]), so you can serve your own routes alongside the API.
+/// Like with Rocket, other requests under `base_path` (e.g. `/api/foo`, or a `POST` to the API) get error `202`, rather than a bare 404 or 405.
+/// The config is checked with [`Config::validate`] first.
+///
+/// To pass the client's IP address on to the [`Authenticator`](crate::data::Authenticator), serve it with [`Router::into_make_service_with_connect_info::<SocketAddr>`](Router::into_make_service_with_connect_info).
+pub fn router(conf: Config, base_path: impl AsRef<str>) -> Result<Router, RunError> {
+    if let Err(problems) = conf.validate() {
+        return Err(RunError::InvalidConfig(problems));
+    }
+
+    let base_path = normalize_base_path(base_path);
+    let under_base_path = format!("{}/{{*rest}}", base_path.trim_end_matches('/'));
+    return Ok(Router::new()
+        .route(&base_path, get(endpoint).fallback(not_found))
+        .route(&under_base_path, any(not_found))
+        .with_state(conf));
+}
+
+/// Runs the server on `address` (e.g. `127.0.0.1:8000`), serving the API at `/api`
+///
+/// The config is checked with [`Config::validate`] first, and the server isn't started if there are any problems.
+///
+/// Returns `Ok(true)` if it succeeds, otherwise returns the config's problems, or the error from binding or serving
+pub async fn run(conf: Config, address: impl tokio::net::ToSocketAddrs) -> Result<bool, RunError> {
+    let app = router(conf, "/api")?;
+    let listener = tokio::net::TcpListener::bind(address).await?;
+
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await?;
+    return Ok(true);
+}
+
+/// Runs the server like [`run`], but with what it listens on set by [`ServerOptions`]; unset options fall back to [`DEFAULT_ADDRESS`] and [`DEFAULT_PORT`]
+///
+/// Unix sockets are supported (on Unix), but TLS isn't; that returns [`RunError::UnsupportedOption`], so either terminate TLS in a reverse proxy, or use the Rocket adapter with the `tls` feature.
+pub async fn run_with_options(conf: Config, options: ServerOptions) -> Result<bool, RunError> {
+    spawn(conf, options).await?.wait().await?;
+    return Ok(true);
+}
+
+/// Starts the server in the background like [`run_with_options`], and returns a [`ServerHandle`] to it once it's listening
+///
+/// The handle has the address it's actually listening on (e.g. the port that was picked, with port `0`), and can gracefully shut it down, letting in-flight requests finish.
+/// Unlike Rocket, axum doesn't stop on Ctrl-C by itself, so that's up to you.
+pub async fn spawn(conf: Config, options: ServerOptions) -> Result<ServerHandle, RunError> {
+    if options.tls.is_some() {
+        return Err(RunError::UnsupportedOption(
+            "the axum adapter doesn't support TLS; use a reverse proxy, or Rocket with the `tls` feature".to_string(),
+        ));
+    }
+
+    let app = router(conf, "/api")?;
+    let (shutdown_sender, shutdown_receiver) = tokio::sync::oneshot::channel::<()>();
+    let shutdown = move || {
+        let _ = shutdown_sender.send(());
+    };
+    let signal = async {
+        let _ = shutdown_receiver.await;
+    };
+
+    if let Some(path) = options.unix_socket {
+        let listener = bind_unix(path)?;
+        let task = tokio::spawn(async move {
+            axum::serve(listener, app)
+                .with_graceful_shutdown(signal)
+                .await?;
+            return Ok(());
+        });
+        return Ok(ServerHandle::new(None, shutdown, task));
+    }
+
+    let address = SocketAddr::new(
+        options.address.unwrap_or(DEFAULT_ADDRESS),
+        options.port.unwrap_or(DEFAULT_PORT),
+    );
+    let listener = tokio::net::TcpListener::bind(address).await?;
+    let address = listener.local_addr()?;
+    let task = tokio::spawn(async move {
+        axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .with_graceful_shutdown(signal)
+        .await?;
+        return Ok(());
+    });
+    return Ok(ServerHandle::new(Some(address), shutdown, task));
+}
+
+/// Binds to the Unix socket at `path`
+#[cfg(unix)]
+fn bind_unix(path: std::path::PathBuf) -> Result<tokio::net::UnixListener, RunError> {
+    return Ok(tokio::net::UnixListener::bind(path)?);
+}
+
+/// Unix sockets aren't a thing here, so this just returns an error
+#[cfg(not(unix))]
+fn bind_unix(_path: std::path::PathBuf) -> Result<tokio::net::TcpListener, RunError> {
+    return Err(RunError::UnsupportedOption(
+        "Unix sockets are only supported on Unix".to_string(),
+    ));
+}
+
+/// The API endpoint; converts the request to an [`ApiRequest`], and the result of [`api::handle`] to a response
+async fn endpoint(State(conf): State<Config>, request: Request) -> Response {
+    let mut api_request = ApiRequest::from_query_string(request.uri().query().unwrap_or_default());
+    api_request.headers = request
+        .headers()
+        .iter()
+        .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+        .collect();
+    api_request.client_ip = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(address)| address.ip());
+
+    return to_response(api::handle(&conf, api_request).await);
+}
+
+/// Responds to requests under the API's path that don't match the endpoint with error `202`, like the Rocket adapter's catcher
+async fn not_found() -> Response {
+    return to_response(api::error_response(TorznabError::NoSuchFunction));
+}
+
+/// Converts an [`ApiResponse`](api::ApiResponse) to an axum response
+fn to_response(response: api::ApiResponse) -> Response {
+    let status = StatusCode::from_u16(response.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    return (
+        status,
+        [(header::CONTENT_TYPE, response.content_type)],
+        response.body,
+    )
+        .into_response();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dummy::{create_empty_config, http_get};
+    use axum::body::{self, Body};
+    use tower::ServiceExt;
+
+    async fn get_body(app: Router, uri: &str) -> (StatusCode, String) {
+        let response = app
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        return (status, String::from_utf8(body.to_vec()).unwrap());
+    }
+
+    #[actix_rt::test]
+    async fn router_serves_api() {
+        let app = router(create_empty_config(), "torznab/api/").unwrap();
+
+        let (status, body) = get_body(app.clone(), "/torznab/api?t=caps").await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains("<caps>"));
+
+        let (status, body) = get_body(
+            app.clone(),
+            "/torznab/api?t=search&q=normal&apikey=letmein&extended=1",
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains(r#"<torznab:attr name="seeders" value="10" />"#));
+
+        let (status, _) = get_body(app, "/api?t=caps").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[actix_rt::test]
+    async fn unknown_paths_under_the_api_are_torznab_errors() {
+        let app = router(create_empty_config(), "/torznab/api").unwrap();
+        for uri in ["/torznab/api/foo", "/torznab/api/foo/bar?t=caps"] {
+            let (status, body) = get_body(app.clone(), uri).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{}", uri);
+            assert!(body.contains(r#"<error code="202""#), "{}", uri);
+        }
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::post("/torznab/api?t=caps")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        // anything outside of it is left alone
+        let (status, _) = get_body(app, "/torznab/other").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        // including when it's served at the root
+        let app = router(create_empty_config(), "/").unwrap();
+        let (status, body) = get_body(app.clone(), "/?t=caps").await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains("<caps>"));
+        let (_, body) = get_body(app, "/foo").await;
+        assert!(body.contains(r#"<error code="202""#));
+    }
+
+    #[actix_rt::test]
+    async fn tls_is_unsupported() {
+        let options = ServerOptions {
+            tls: Some(crate::server::TlsOptions {
+                certs: "cert.pem".into(),
+                key: "key.pem".into(),
+            }),
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            run_with_options(create_empty_config(), options).await,
+            Err(RunError::UnsupportedOption(_))
+        ));
+    }
+
+    #[actix_rt::test]
+    async fn spawn_reports_address_and_shuts_down() {
+        let options = ServerOptions {
+            port: Some(0),
+            ..Default::default()
+        };
+        let server = spawn(create_empty_config(), options).await.unwrap();
+        let address = server.local_addr().unwrap();
+        assert_ne!(address.port(), 0);
+
+        let response = tokio::task::spawn_blocking(move || {
+            http_get(
+                std::net::TcpStream::connect(address).unwrap(),
+                "/api?t=caps",
+            )
+        })
+        .await
+        .unwrap();
+        assert!(response.starts_with("HTTP/1.0 200 OK"));
+        assert!(response.contains("<caps>"));
+
+        server.shutdown().await.unwrap();
+        assert!(std::net::TcpStream::connect(address).is_err());
+    }
+
+    #[cfg(unix)]
+    #[actix_rt::test]
+    async fn spawn_on_unix_socket() {
+        let path =
+            std::env::temp_dir().join(format!("torznab-toolkit-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let options = ServerOptions {
+            unix_socket: Some(path.clone()),
+            ..Default::default()
+        };
+        let server = spawn(create_empty_config(), options).await.unwrap();
+        assert_eq!(server.local_addr(), None);
+
+        let socket = path.clone();
+        let response = tokio::task::spawn_blocking(move || {
+            http_get(
+                std::os::unix::net::UnixStream::connect(socket).unwrap(),
+                "/api?t=caps",
+            )
+        })
+        .await
+        .unwrap();
+        assert!(response.contains("<caps>"));
+
+        server.shutdown().await.unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
+}
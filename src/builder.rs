@@ -0,0 +1,854 @@
+//! Builders for the structs in [`data`](crate::data), which check that what they're building is valid
+//!
+//! Each builder is created by the `builder()` function of the struct it builds, e.g. [`Torrent::builder`], and is finished with `build()`, which returns a [`BuildError`] if something's wrong, rather than letting invalid data reach the API.
+//!
+//! Example:
+//! ```
+//! use torznab_toolkit::data::{Caps, Category, Config, SearchInfo, SearchParameters, Torrent};
+//!
+//! fn search(_parameters: SearchParameters) -> Result<Vec<Torrent>, String> {
+//!     let torrent = Torrent::builder("totally normal torrent", 2484345508)
+//!         .category(2010)
+//!         .torrent_file_url("http://localhost/totally-normal.torrent")
+//!         .magnet_uri("magnet:?xt=urn:btih:blahblahblahdothechachacha")
+//!         .build()
+//!         .map_err(|e| e.to_string())?;
+//!     return Ok(vec![torrent]);
+//! }
+//!
+//! let caps = Caps::builder()
+//!     .server_info("title", "My indexer")
+//!     .limits(100, 50)
+//!     .search_info(SearchInfo::builder("search").supported_param("q").build().unwrap())
+//!     .category(Category::builder(2000, "Movies").subcategory(2010, "Foreign").build().unwrap())
+//!     .build()
+//!     .unwrap();
+//!
+//! let config = Config::builder(caps).search(search).build().unwrap();
+//! ```
+use crate::data::*;
+use crate::validation::ConfigProblem;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// An error from `build()`, describing what's wrong with the struct being built
+pub enum BuildError {
+    /// A required field wasn't set
+    MissingField {
+        /// The name of the struct being built, e.g. `Torrent`
+        item: &'static str,
+        /// The name of the missing field
+        field: &'static str,
+    },
+    /// A field was set, but to an invalid value
+    InvalidField {
+        /// The name of the struct being built, e.g. `Torrent`
+        item: &'static str,
+        /// The name of the invalid field
+        field: &'static str,
+        /// Why the value is invalid
+        reason: String,
+    },
+    /// The [`Caps`] (or a [`Config`]'s caps) were built, but have problems; see [`Caps::validate`]
+    InvalidCaps(Vec<ConfigProblem>),
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildError::MissingField { item, field } => {
+                return write!(f, "{}: `{}` is required, but wasn't set", item, field);
+            }
+            BuildError::InvalidField {
+                item,
+                field,
+                reason,
+            } => {
+                return write!(f, "{}: `{}` is invalid: {}", item, field, reason);
+            }
+            BuildError::InvalidCaps(problems) => {
+                let problems: Vec<String> = problems.iter().map(|p| p.to_string()).collect();
+                return write!(f, "Caps: {}", problems.join("; "));
+            }
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
+
+/// Shorthand for creating a [`BuildError::InvalidField`]
+fn invalid(item: &'static str, field: &'static str, reason: impl AsRef<str>) -> BuildError {
+    return BuildError::InvalidField {
+        item,
+        field,
+        reason: reason.as_ref().to_string(),
+    };
+}
+
+impl Config {
+    /// Creates a [`ConfigBuilder`] with the given capabilities
+    pub fn builder(caps: Caps) -> ConfigBuilder {
+        return ConfigBuilder {
+            search: None,
+            auth: None,
+            caps,
+            filter_categories: false,
+            unsupported_params: UnsupportedParams::default(),
+            handlers: SearchRouter::new(),
+        };
+    }
+}
+
+/// A builder for [`Config`]; see [`Config::builder`]
+///
+/// A search backend is required: either one for all search types ([`search`](ConfigBuilder::search)), or one for each ([`search_handler`](ConfigBuilder::search_handler)), or both. The authenticator is optional.
+pub struct ConfigBuilder {
+    search: Option<Arc<dyn SearchBackend>>,
+    auth: Option<Arc<dyn Authenticator>>,
+    caps: Caps,
+    filter_categories: bool,
+    unsupported_params: UnsupportedParams,
+    handlers: SearchRouter,
+}
+
+impl ConfigBuilder {
+    /// Sets the search backend (or search function); see [`SearchBackend`]
+    pub fn search(mut self, search: impl SearchBackend + 'static) -> ConfigBuilder {
+        self.search = Some(Arc::new(search));
+        return self;
+    }
+
+    /// Registers a backend for just one search type, e.g. `tv-search`; see [`SearchRouter`]
+    ///
+    /// `search_info` (i.e. the search type, whether it's available, and its supported parameters) is added to the caps' `searching` when the config is built, replacing any that was listed for the same type, so the caps always match the registered handlers.
+    /// Searches of types without their own handler go to the backend set with [`search`](ConfigBuilder::search), if there is one; if there isn't, any other search types listed in the caps are marked as unavailable, since they'd just get a `203` error.
+    ///
+    /// Example:
+    /// ```
+    /// # use torznab_toolkit::data::*;
+    /// # fn tv_search(_: SearchParameters) -> Result<Vec<Torrent>, String> { return Ok(vec![]); }
+    /// # fn movie_search(_: SearchParameters) -> Result<Vec<Torrent>, String> { return Ok(vec![]); }
+    /// # let caps = Caps::builder().limits(100, 50).category(Category::builder(2000, "Movies").build().unwrap()).build().unwrap();
+    /// let config = Config::builder(caps)
+    ///     .search_handler(
+    ///         SearchInfo::builder("tv-search").supported_params(["q", "season", "ep"]).build().unwrap(),
+    ///         tv_search,
+    ///     )
+    ///     .search_handler(
+    ///         SearchInfo::builder("movie-search").supported_params(["q", "imdbid"]).build().unwrap(),
+    ///         movie_search,
+    ///     )
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(config.caps.searching.len(), 2);
+    /// ```
+    pub fn search_handler(
+        mut self,
+        search_info: SearchInfo,
+        backend: impl SearchBackend + 'static,
+    ) -> ConfigBuilder {
+        self.handlers = self.handlers.handler(search_info, backend);
+        return self;
+    }
+
+    /// Sets the authenticator (or auth function); see [`Authenticator`]
+    pub fn auth(mut self, auth: impl Authenticator + 'static) -> ConfigBuilder {
+        self.auth = Some(Arc::new(auth));
+        return self;
+    }
+
+    /// Sets whether torrents that aren't in the requested categories are filtered out; see [`Config::filter_categories`]
+    pub fn filter_categories(mut self, filter_categories: bool) -> ConfigBuilder {
+        self.filter_categories = filter_categories;
+        return self;
+    }
+
+    /// Sets what's done with search parameters that the search type doesn't support; see [`UnsupportedParams`]
+    pub fn unsupported_params(mut self, unsupported_params: UnsupportedParams) -> ConfigBuilder {
+        self.unsupported_params = unsupported_params;
+        return self;
+    }
+
+    /// Builds the [`Config`], failing if no search backend was set or if the caps don't pass [`Caps::validate`]
+    ///
+    /// If any handlers were registered with [`search_handler`](ConfigBuilder::search_handler), their [`SearchInfo`]s are added to the caps' `searching`, and, without a [`search`](ConfigBuilder::search) backend to fall back on, the search types without a handler are marked as unavailable.
+    pub fn build(self) -> Result<Config, BuildError> {
+        let mut caps = self.caps;
+        let search_infos = self.handlers.search_infos();
+
+        let search: Arc<dyn SearchBackend> = if search_infos.is_empty() {
+            self.search.ok_or(BuildError::MissingField {
+                item: "Config",
+                field: "search",
+            })?
+        } else {
+            let handled: Vec<String> = search_infos
+                .iter()
+                .map(|info| info.search_type.clone())
+                .collect();
+            for search_info in search_infos {
+                match caps
+                    .searching
+                    .iter_mut()
+                    .find(|info| info.search_type == search_info.search_type)
+                {
+                    Some(info) => *info = search_info,
+                    None => caps.searching.push(search_info),
+                }
+            }
+
+            let mut router = self.handlers;
+            match self.search {
+                Some(fallback) => {
+                    router = router.shared_fallback(fallback);
+                }
+                None => {
+                    // nothing would answer these, so they can't be advertised as available
+                    for info in caps.searching.iter_mut() {
+                        if !handled.contains(&info.search_type) {
+                            info.available = false;
+                        }
+                    }
+                }
+            }
+            Arc::new(router)
+        };
+        caps.validate().map_err(BuildError::InvalidCaps)?;
+
+        return Ok(Config {
+            search,
+            auth: self.auth,
+            caps,
+            filter_categories: self.filter_categories,
+            unsupported_params: self.unsupported_params,
+        });
+    }
+}
+
+impl Caps {
+    /// Creates an empty [`CapsBuilder`]
+    pub fn builder() -> CapsBuilder {
+        return CapsBuilder {
+            server_info: HashMap::new(),
+            limits: None,
+            searching: Vec::new(),
+            categories: Vec::new(),
+            genres: Vec::new(),
+            tags: Vec::new(),
+        };
+    }
+}
+
+/// A builder for [`Caps`]; see [`Caps::builder`]
+///
+/// The limits and at least one category are required; genres and tags are only listed if any are added.
+pub struct CapsBuilder {
+    server_info: HashMap<String, String>,
+    limits: Option<Limits>,
+    searching: Vec<SearchInfo>,
+    categories: Vec<Category>,
+    genres: Vec<Genre>,
+    tags: Vec<Tag>,
+}
+
+impl CapsBuilder {
+    /// Adds a piece of server info, e.g. `title`, `version`, `email`, `url`, or `image`
+    pub fn server_info(mut self, key: impl AsRef<str>, value: impl AsRef<str>) -> CapsBuilder {
+        self.server_info
+            .insert(key.as_ref().to_string(), value.as_ref().to_string());
+        return self;
+    }
+
+    /// Sets the maximum and default number of results per search; see [`Limits`]
+    pub fn limits(mut self, max: u32, default: u32) -> CapsBuilder {
+        self.limits = Some(Limits { max, default });
+        return self;
+    }
+
+    /// Adds info about a type of search; see [`SearchInfo::builder`]
+    pub fn search_info(mut self, search_info: SearchInfo) -> CapsBuilder {
+        self.searching.push(search_info);
+        return self;
+    }
+
+    /// Adds a category; see [`Category::builder`]
+    pub fn category(mut self, category: Category) -> CapsBuilder {
+        self.categories.push(category);
+        return self;
+    }
+
+    /// Adds several categories, e.g. from [`categories::to_categories`](crate::categories::to_categories)
+    pub fn categories(mut self, categories: impl IntoIterator<Item = Category>) -> CapsBuilder {
+        self.categories.extend(categories);
+        return self;
+    }
+
+    /// Adds a genre
+    pub fn genre(mut self, id: u32, category_id: u32, name: impl AsRef<str>) -> CapsBuilder {
+        self.genres.push(Genre {
+            id,
+            category_id,
+            name: name.as_ref().to_string(),
+        });
+        return self;
+    }
+
+    /// Adds a tag
+    pub fn tag(mut self, name: impl AsRef<str>, description: impl AsRef<str>) -> CapsBuilder {
+        self.tags.push(Tag {
+            name: name.as_ref().to_string(),
+            description: description.as_ref().to_string(),
+        });
+        return self;
+    }
+
+    /// Builds the [`Caps`]
+    ///
+    /// Fails if the limits weren't set, if there are no categories, or if the result doesn't pass [`Caps::validate`]
+    pub fn build(self) -> Result<Caps, BuildError> {
+        let limits = self.limits.ok_or(BuildError::MissingField {
+            item: "Caps",
+            field: "limits",
+        })?;
+
+        if self.categories.is_empty() {
+            return Err(BuildError::MissingField {
+                item: "Caps",
+                field: "categories",
+            });
+        }
+
+        let mut server_info = None;
+        if !self.server_info.is_empty() {
+            server_info = Some(self.server_info);
+        }
+        let mut genres = None;
+        if !self.genres.is_empty() {
+            genres = Some(self.genres);
+        }
+        let mut tags = None;
+        if !self.tags.is_empty() {
+            tags = Some(self.tags);
+        }
+
+        let caps = Caps {
+            server_info,
+            limits,
+            searching: self.searching,
+            categories: self.categories,
+            genres,
+            tags,
+        };
+        caps.validate().map_err(BuildError::InvalidCaps)?;
+        return Ok(caps);
+    }
+}
+
+impl Category {
+    /// Creates a [`CategoryBuilder`] for a category with the given ID and name
+    pub fn builder(id: u32, name: impl AsRef<str>) -> CategoryBuilder {
+        return CategoryBuilder {
+            id,
+            name: name.as_ref().to_string(),
+            subcategories: Vec::new(),
+        };
+    }
+}
+
+/// A builder for [`Category`]; see [`Category::builder`]
+pub struct CategoryBuilder {
+    id: u32,
+    name: String,
+    subcategories: Vec<Subcategory>,
+}
+
+impl CategoryBuilder {
+    /// Adds a subcategory with the given ID and name
+    pub fn subcategory(mut self, id: u32, name: impl AsRef<str>) -> CategoryBuilder {
+        self.subcategories.push(Subcategory {
+            id,
+            name: name.as_ref().to_string(),
+        });
+        return self;
+    }
+
+    /// Builds the [`Category`]
+    ///
+    /// Fails if the category or any subcategory has an empty name, or if any subcategory ID is repeated or the same as the category's
+    pub fn build(self) -> Result<Category, BuildError> {
+        if self.name.trim().is_empty() {
+            return Err(invalid("Category", "name", "must not be empty"));
+        }
+
+        for (i, subcategory) in self.subcategories.iter().enumerate() {
+            if subcategory.name.trim().is_empty() {
+                return Err(invalid(
+                    "Category",
+                    "subcategories",
+                    format!("subcategory {} has an empty name", subcategory.id),
+                ));
+            }
+            if subcategory.id == self.id
+                || self.subcategories[..i]
+                    .iter()
+                    .any(|other| other.id == subcategory.id)
+            {
+                return Err(invalid(
+                    "Category",
+                    "subcategories",
+                    format!("ID {} is used more than once", subcategory.id),
+                ));
+            }
+        }
+
+        return Ok(Category {
+            id: self.id,
+            name: self.name,
+            subcategories: self.subcategories,
+        });
+    }
+}
+
+impl SearchInfo {
+    /// Creates a [`SearchInfoBuilder`] for the given search type; it's available by default
+    ///
+    /// Search types: `search`, `tv-search`, `movie-search`, `audio-search`, `book-search`
+    pub fn builder(search_type: impl AsRef<str>) -> SearchInfoBuilder {
+        return SearchInfoBuilder {
+            search_type: search_type.as_ref().to_string(),
+            available: true,
+            supported_params: Vec::new(),
+        };
+    }
+}
+
+/// A builder for [`SearchInfo`]; see [`SearchInfo::builder`]
+pub struct SearchInfoBuilder {
+    search_type: String,
+    available: bool,
+    supported_params: Vec<String>,
+}
+
+impl SearchInfoBuilder {
+    /// Sets whether this search type is available
+    pub fn available(mut self, available: bool) -> SearchInfoBuilder {
+        self.available = available;
+        return self;
+    }
+
+    /// Adds a supported parameter, e.g. `q`
+    pub fn supported_param(mut self, param: impl AsRef<str>) -> SearchInfoBuilder {
+        self.supported_params.push(param.as_ref().to_string());
+        return self;
+    }
+
+    /// Adds several supported parameters, e.g. `["q", "season", "ep"]`
+    pub fn supported_params(
+        mut self,
+        params: impl IntoIterator<Item = impl AsRef<str>>,
+    ) -> SearchInfoBuilder {
+        for param in params {
+            self.supported_params.push(param.as_ref().to_string());
+        }
+        return self;
+    }
+
+    /// Builds the [`SearchInfo`], failing if the search type isn't one of the known types ([`SEARCH_TYPES`])
+    pub fn build(self) -> Result<SearchInfo, BuildError> {
+        if !SEARCH_TYPES.contains(&self.search_type.as_str()) {
+            return Err(invalid(
+                "SearchInfo",
+                "search_type",
+                format!(
+                    "`{}` isn't one of {}",
+                    self.search_type,
+                    SEARCH_TYPES.join(", ")
+                ),
+            ));
+        }
+
+        return Ok(SearchInfo {
+            search_type: self.search_type,
+            available: self.available,
+            supported_params: self.supported_params,
+        });
+    }
+}
+
+impl Torrent {
+    /// Creates a [`TorrentBuilder`] for a torrent with the given title and size (in bytes)
+    pub fn builder(title: impl AsRef<str>, size: u64) -> TorrentBuilder {
+        return TorrentBuilder {
+            title: title.as_ref().to_string(),
+            description: None,
+            size,
+            category_ids: Vec::new(),
+            torrent_file_url: None,
+            magnet_uri: None,
+            link: None,
+            pub_date: None,
+            attributes: TorrentAttributes::default(),
+            other_attributes: HashMap::new(),
+        };
+    }
+}
+
+/// A builder for [`Torrent`]; see [`Torrent::builder`]
+///
+/// At least one category, and a .torrent URL or magnet URI, are required.
+pub struct TorrentBuilder {
+    title: String,
+    description: Option<String>,
+    size: u64,
+    category_ids: Vec<u32>,
+    torrent_file_url: Option<String>,
+    magnet_uri: Option<String>,
+    link: Option<String>,
+    pub_date: Option<String>,
+    attributes: TorrentAttributes,
+    other_attributes: HashMap<String, String>,
+}
+
+impl TorrentBuilder {
+    /// Sets the description
+    pub fn description(mut self, description: impl AsRef<str>) -> TorrentBuilder {
+        self.description = Some(description.as_ref().to_string());
+        return self;
+    }
+
+    /// Adds a (sub)category ID
+    pub fn category(mut self, id: u32) -> TorrentBuilder {
+        self.category_ids.push(id);
+        return self;
+    }
+
+    /// Sets the URL of the `.torrent` file
+    pub fn torrent_file_url(mut self, url: impl AsRef<str>) -> TorrentBuilder {
+        self.torrent_file_url = Some(url.as_ref().to_string());
+        return self;
+    }
+
+    /// Sets the magnet URI
+    pub fn magnet_uri(mut self, uri: impl AsRef<str>) -> TorrentBuilder {
+        self.magnet_uri = Some(uri.as_ref().to_string());
+        return self;
+    }
+
+    /// Sets the link to a webpage for the torrent
+    pub fn link(mut self, link: impl AsRef<str>) -> TorrentBuilder {
+        self.link = Some(link.as_ref().to_string());
+        return self;
+    }
+
+    /// Sets when the torrent was published, as an RFC 2822 date (e.g. `Tue, 10 Jun 2003 04:00:00 GMT`)
+    pub fn pub_date(mut self, pub_date: impl AsRef<str>) -> TorrentBuilder {
+        self.pub_date = Some(pub_date.as_ref().to_string());
+        return self;
+    }
+
+    /// Sets the number of seeders
+    pub fn seeders(mut self, seeders: u32) -> TorrentBuilder {
+        self.attributes.seeders = Some(seeders);
+        return self;
+    }
+
+    /// Sets the number of leechers
+    pub fn leechers(mut self, leechers: u32) -> TorrentBuilder {
+        self.attributes.leechers = Some(leechers);
+        return self;
+    }
+
+    /// Sets the number of peers
+    pub fn peers(mut self, peers: u32) -> TorrentBuilder {
+        self.attributes.peers = Some(peers);
+        return self;
+    }
+
+    /// Sets the infohash
+    pub fn infohash(mut self, infohash: impl AsRef<str>) -> TorrentBuilder {
+        self.attributes.infohash = Some(infohash.as_ref().to_string());
+        return self;
+    }
+
+    /// Sets all the predefined attributes at once, replacing any set before; see [`TorrentAttributes`]
+    pub fn attributes(mut self, attributes: TorrentAttributes) -> TorrentBuilder {
+        self.attributes = attributes;
+        return self;
+    }
+
+    /// Adds a custom attribute, i.e. one that isn't in [`TorrentAttributes`]
+    pub fn attribute(mut self, name: impl AsRef<str>, value: impl AsRef<str>) -> TorrentBuilder {
+        self.other_attributes
+            .insert(name.as_ref().to_string(), value.as_ref().to_string());
+        return self;
+    }
+
+    /// Builds the [`Torrent`]
+    ///
+    /// Fails if the title is empty, if there are no categories, or if there's neither a .torrent URL nor a magnet URI
+    pub fn build(self) -> Result<Torrent, BuildError> {
+        if self.title.trim().is_empty() {
+            return Err(invalid("Torrent", "title", "must not be empty"));
+        }
+        if self.category_ids.is_empty() {
+            return Err(BuildError::MissingField {
+                item: "Torrent",
+                field: "category_ids",
+            });
+        }
+
+        let torrent_file_url = self.torrent_file_url.filter(|url| !url.trim().is_empty());
+        let magnet_uri = self.magnet_uri.filter(|uri| !uri.trim().is_empty());
+        if torrent_file_url.is_none() && magnet_uri.is_none() {
+            return Err(invalid(
+                "Torrent",
+                "torrent_file_url",
+                "either a .torrent file URL or a magnet URI is required",
+            ));
+        }
+        if let Some(uri) = &magnet_uri {
+            if !uri.starts_with("magnet:") {
+                return Err(invalid(
+                    "Torrent",
+                    "magnet_uri",
+                    "must start with `magnet:`",
+                ));
+            }
+        }
+
+        let mut other_attributes = None;
+        if !self.other_attributes.is_empty() {
+            other_attributes = Some(self.other_attributes);
+        }
+
+        return Ok(Torrent {
+            title: self.title,
+            description: self.description,
+            size: self.size,
+            category_ids: self.category_ids,
+            torrent_file_url,
+            magnet_uri,
+            link: self.link,
+            pub_date: self.pub_date,
+            attributes: self.attributes,
+            other_attributes,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn torrent_requires_url_or_magnet() {
+        let result = Torrent::builder("a", 1).category(1010).build();
+        assert!(matches!(
+            result,
+            Err(BuildError::InvalidField {
+                field: "torrent_file_url",
+                ..
+            })
+        ));
+
+        let torrent = Torrent::builder("a", 1)
+            .category(1010)
+            .magnet_uri("magnet:?xt=urn:btih:a")
+            .build()
+            .unwrap();
+        assert_eq!(torrent.torrent_file_url, None);
+    }
+
+    #[actix_rt::test]
+    async fn search_handlers_fill_in_caps_and_get_their_searches() {
+        let caps = Caps::builder()
+            .limits(100, 20)
+            .search_info(
+                SearchInfo::builder("tv-search")
+                    .available(false)
+                    .build()
+                    .unwrap(),
+            )
+            .category(Category::builder(5000, "TV").build().unwrap())
+            .build()
+            .unwrap();
+        let tv = |_: SearchParameters| -> Result<Vec<Torrent>, String> { Err("tv".to_string()) };
+        let other =
+            |_: SearchParameters| -> Result<Vec<Torrent>, String> { Err("other".to_string()) };
+
+        let config = Config::builder(caps)
+            .search(other)
+            .search_handler(
+                SearchInfo::builder("tv-search")
+                    .supported_params(["q", "season"])
+                    .build()
+                    .unwrap(),
+                tv,
+            )
+            .build()
+            .unwrap();
+        assert_eq!(
+            config.caps.searching,
+            vec![SearchInfo {
+                search_type: "tv-search".to_string(),
+                available: true,
+                supported_params: vec!["q".to_string(), "season".to_string()],
+            }]
+        );
+
+        let mut parameters = SearchParameters::new("tv-search", 20);
+        assert_eq!(
+            config.search.search(parameters.clone()).await,
+            Err(TorznabError::Unknown("tv".to_string()))
+        );
+        parameters.search_type = "movie-search".to_string();
+        assert_eq!(
+            config.search.search(parameters).await,
+            Err(TorznabError::Unknown("other".to_string()))
+        );
+    }
+
+    #[actix_rt::test]
+    async fn searches_without_handlers_or_fallback_are_unavailable() {
+        let caps = Caps::builder()
+            .limits(100, 20)
+            .search_info(
+                SearchInfo::builder("search")
+                    .supported_param("q")
+                    .build()
+                    .unwrap(),
+            )
+            .search_info(
+                SearchInfo::builder("movie-search")
+                    .supported_param("q")
+                    .build()
+                    .unwrap(),
+            )
+            .category(Category::builder(5000, "TV").build().unwrap())
+            .build()
+            .unwrap();
+        let tv = |_: SearchParameters| -> Result<Vec<Torrent>, String> { Ok(vec![]) };
+
+        let config = Config::builder(caps)
+            .search_handler(
+                SearchInfo::builder("tv-search")
+                    .supported_param("q")
+                    .build()
+                    .unwrap(),
+                tv,
+            )
+            .build()
+            .unwrap();
+        let available: Vec<(&str, bool)> = config
+            .caps
+            .searching
+            .iter()
+            .map(|info| (info.search_type.as_str(), info.available))
+            .collect();
+        assert_eq!(
+            available,
+            vec![
+                ("search", false),
+                ("movie-search", false),
+                ("tv-search", true)
+            ]
+        );
+
+        // so they're rejected before reaching the router
+        let response = crate::api::handle(
+            &config,
+            crate::api::ApiRequest::from_query_string("t=movie&q=x"),
+        )
+        .await;
+        assert!(response.body.contains(r#"code="203""#));
+        let response = crate::api::handle(
+            &config,
+            crate::api::ApiRequest::from_query_string("t=tvsearch&q=x"),
+        )
+        .await;
+        assert_eq!(response.status, 200);
+    }
+
+    #[test]
+    fn caps_rejects_default_over_max() {
+        let result = Caps::builder()
+            .limits(10, 20)
+            .category(Category::builder(1000, "a").build().unwrap())
+            .build();
+        assert_eq!(
+            result,
+            Err(BuildError::InvalidCaps(vec![
+                ConfigProblem::InvalidLimits {
+                    max: 10,
+                    default: 20
+                }
+            ]))
+        );
+    }
+
+    #[test]
+    fn caps_rejects_zero_default() {
+        let result = Caps::builder()
+            .limits(10, 0)
+            .category(Category::builder(1000, "a").build().unwrap())
+            .build();
+        assert_eq!(
+            result,
+            Err(BuildError::InvalidCaps(vec![
+                ConfigProblem::InvalidLimits {
+                    max: 10,
+                    default: 0
+                }
+            ]))
+        );
+    }
+
+    #[test]
+    fn caps_rejects_what_validate_rejects() {
+        let result = Caps::builder()
+            .limits(100, 20)
+            .category(
+                Category::builder(5000, "TV")
+                    .subcategory(2040, "Movies/HD")
+                    .build()
+                    .unwrap(),
+            )
+            .category(Category::builder(5000, "TV again").build().unwrap())
+            .genre(1, 7000, "Fiction")
+            .tag("a", "b")
+            .tag("a", "c")
+            .build();
+        assert_eq!(
+            result,
+            Err(BuildError::InvalidCaps(vec![
+                ConfigProblem::SubcategoryOutsideParent {
+                    category_id: 5000,
+                    subcategory_id: 2040
+                },
+                ConfigProblem::DuplicateCategoryId(5000),
+                ConfigProblem::UnknownGenreCategory {
+                    genre_id: 1,
+                    category_id: 7000
+                },
+                ConfigProblem::DuplicateTagName("a".to_string()),
+            ]))
+        );
+    }
+
+    #[test]
+    fn config_rejects_invalid_caps() {
+        // e.g. caps deserialized from a file, which skip the caps builder
+        let mut caps = Caps::builder()
+            .limits(100, 20)
+            .category(Category::builder(5000, "TV").build().unwrap())
+            .build()
+            .unwrap();
+        caps.limits.default = 0;
+        let search = |_: SearchParameters| -> Result<Vec<Torrent>, String> { Ok(vec![]) };
+        let result = Config::builder(caps).search(search).build();
+        assert!(matches!(result, Err(BuildError::InvalidCaps(_))));
+    }
+}
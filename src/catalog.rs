@@ -0,0 +1,381 @@
+//! A simple in-memory [`SearchBackend`] over a fixed list of torrents, e.g. loaded from a JSON or CSV file
+//!
+//! This is what the `torznab-toolkit` binary serves, but it's also handy for demos and tests.
+//! It only matches the text query (`q`); to filter by category too, enable [`Config::filter_categories`](crate::data::Config::filter_categories), and `offset` and `limit` are applied by torznab-toolkit as usual.
+//!
+//! A JSON catalog is a list of [`Torrent`]s, with the same field names:
+//! ```json
+//! [
+//!     {
+//!         "title": "totally normal torrent",
+//!         "size": 2484345508,
+//!         "category_ids": [5040],
+//!         "magnet_uri": "magnet:?xt=urn:btih:blahblahblahdothechachacha",
+//!         "attributes": { "seeders": 10 }
+//!     }
+//! ]
+//! ```
+//!
+//! A CSV catalog has a header row, with the columns `title`, `size`, and `categories` (separated by spaces, commas, or semicolons), and optionally `description`, `torrent_file_url`, `magnet_uri`, `link`, `pub_date`, `seeders`, `leechers`, `peers`, and `infohash`:
+//! ```csv
+//! title,size,categories,magnet_uri,seeders
+//! totally normal torrent,2484345508,5040,magnet:?xt=urn:btih:blahblahblahdothechachacha,10
+//! ```
+//!
+//! JSON needs the `json` feature (enabled by default), and CSV needs the `csv` feature. Every torrent is checked like [`Torrent::builder`] does, e.g. that it has a .torrent URL or magnet URI.
+use crate::builder::BuildError;
+use crate::data::{SearchBackend, SearchParameters, SearchResults, Torrent, TorznabError};
+use async_trait::async_trait;
+use std::fmt;
+use std::path::Path;
+
+#[derive(Debug)]
+/// An error from loading a [`Catalog`]
+pub enum CatalogError {
+    /// The file couldn't be read
+    Io(std::io::Error),
+    /// The file couldn't be parsed; contains the parser's error
+    Parse(String),
+    /// The file's extension isn't one of the formats that are enabled (`json`, `csv`); contains the extension
+    UnknownFormat(String),
+    /// A torrent isn't valid (see [`Torrent::builder`])
+    Invalid {
+        /// The position of the torrent in the catalog, starting from 0
+        index: usize,
+        /// What's wrong with it
+        error: BuildError,
+    },
+}
+
+impl fmt::Display for CatalogError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CatalogError::Io(e) => {
+                return write!(f, "{}", e);
+            }
+            CatalogError::Parse(e) => {
+                return write!(f, "couldn't parse catalog: {}", e);
+            }
+            CatalogError::UnknownFormat(extension) => {
+                return write!(
+                    f,
+                    "unknown catalog file format `{}`; the enabled formats are: {}",
+                    extension,
+                    FORMATS.join(", ")
+                );
+            }
+            CatalogError::Invalid { index, error } => {
+                return write!(f, "torrent #{} in the catalog is invalid: {}", index, error);
+            }
+        }
+    }
+}
+
+impl std::error::Error for CatalogError {}
+
+impl From<std::io::Error> for CatalogError {
+    fn from(e: std::io::Error) -> CatalogError {
+        return CatalogError::Io(e);
+    }
+}
+
+/// The file extensions of the formats that are enabled
+const FORMATS: &[&str] = &[
+    #[cfg(feature = "json")]
+    "json",
+    #[cfg(feature = "csv")]
+    "csv",
+];
+
+#[derive(Debug, Clone, Default, PartialEq)]
+/// An in-memory list of torrents, searchable by their titles
+pub struct Catalog {
+    /// The torrents, in the order they're listed in search results
+    torrents: Vec<Torrent>,
+}
+
+impl Catalog {
+    /// Creates a catalog of `torrents`, which are listed in search results in the same order
+    pub fn new(torrents: Vec<Torrent>) -> Catalog {
+        return Catalog { torrents };
+    }
+
+    /// Returns all the torrents in the catalog
+    pub fn torrents(&self) -> &[Torrent] {
+        return &self.torrents;
+    }
+
+    /// Loads a catalog from a file, with the format picked by its extension (`.json` or `.csv`)
+    ///
+    /// Returns [`CatalogError::UnknownFormat`] if the format's feature isn't enabled.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Catalog, CatalogError> {
+        let path = path.as_ref();
+        let extension = path
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        if !FORMATS.contains(&extension.as_str()) {
+            return Err(CatalogError::UnknownFormat(extension));
+        }
+
+        match extension.as_str() {
+            #[cfg(feature = "json")]
+            "json" => {
+                return Catalog::from_json(&std::fs::read_to_string(path)?);
+            }
+            #[cfg(feature = "csv")]
+            "csv" => {
+                return Catalog::from_csv(std::fs::File::open(path)?);
+            }
+            _ => {
+                return Err(CatalogError::UnknownFormat(extension));
+            }
+        }
+    }
+
+    /// Loads a catalog from a JSON list of [`Torrent`]s; requires the `json` feature
+    #[cfg(feature = "json")]
+    pub fn from_json(json: &str) -> Result<Catalog, CatalogError> {
+        let torrents: Vec<Torrent> =
+            serde_json::from_str(json).map_err(|e| CatalogError::Parse(e.to_string()))?;
+
+        let mut checked = Vec::new();
+        for (index, torrent) in torrents.into_iter().enumerate() {
+            checked.push(rebuild(torrent).map_err(|error| CatalogError::Invalid { index, error })?);
+        }
+        return Ok(Catalog::new(checked));
+    }
+
+    /// Loads a catalog from CSV (see the [module docs](self) for the columns); requires the `csv` feature
+    #[cfg(feature = "csv")]
+    pub fn from_csv(reader: impl std::io::Read) -> Result<Catalog, CatalogError> {
+        let mut torrents = Vec::new();
+        let mut reader = csv::Reader::from_reader(reader);
+        for (index, row) in reader.deserialize::<CsvRow>().enumerate() {
+            let row = row.map_err(|e| CatalogError::Parse(e.to_string()))?;
+            torrents.push(row.into_torrent(index)?);
+        }
+        return Ok(Catalog::new(torrents));
+    }
+}
+
+/// Runs a deserialized torrent through [`Torrent::builder`], so it's checked the same way as any other
+#[cfg(feature = "json")]
+fn rebuild(torrent: Torrent) -> Result<Torrent, BuildError> {
+    let mut builder = Torrent::builder(&torrent.title, torrent.size).attributes(torrent.attributes);
+    for id in torrent.category_ids {
+        builder = builder.category(id);
+    }
+    if let Some(description) = torrent.description {
+        builder = builder.description(description);
+    }
+    if let Some(url) = torrent.torrent_file_url {
+        builder = builder.torrent_file_url(url);
+    }
+    if let Some(uri) = torrent.magnet_uri {
+        builder = builder.magnet_uri(uri);
+    }
+    if let Some(link) = torrent.link {
+        builder = builder.link(link);
+    }
+    if let Some(pub_date) = torrent.pub_date {
+        builder = builder.pub_date(pub_date);
+    }
+    for (name, value) in torrent.other_attributes.unwrap_or_default() {
+        builder = builder.attribute(name, value);
+    }
+    return builder.build();
+}
+
+#[cfg(feature = "csv")]
+#[derive(Debug, serde::Deserialize)]
+/// A row of a CSV catalog
+struct CsvRow {
+    title: String,
+    size: u64,
+    /// The category IDs, separated by spaces, commas, or semicolons
+    categories: String,
+    description: Option<String>,
+    torrent_file_url: Option<String>,
+    magnet_uri: Option<String>,
+    link: Option<String>,
+    pub_date: Option<String>,
+    seeders: Option<u32>,
+    leechers: Option<u32>,
+    peers: Option<u32>,
+    infohash: Option<String>,
+}
+
+#[cfg(feature = "csv")]
+impl CsvRow {
+    /// Builds the torrent in this row; `index` is the row's position, for errors
+    fn into_torrent(self, index: usize) -> Result<Torrent, CatalogError> {
+        let mut builder = Torrent::builder(&self.title, self.size);
+        for id in self
+            .categories
+            .split([' ', ',', ';'])
+            .filter(|id| !id.is_empty())
+        {
+            let id = id.parse::<u32>().map_err(|_| {
+                CatalogError::Parse(format!("row {}: invalid category ID `{}`", index + 1, id))
+            })?;
+            builder = builder.category(id);
+        }
+
+        if let Some(description) = self.description {
+            builder = builder.description(description);
+        }
+        if let Some(url) = self.torrent_file_url {
+            builder = builder.torrent_file_url(url);
+        }
+        if let Some(uri) = self.magnet_uri {
+            builder = builder.magnet_uri(uri);
+        }
+        if let Some(link) = self.link {
+            builder = builder.link(link);
+        }
+        if let Some(pub_date) = self.pub_date {
+            builder = builder.pub_date(pub_date);
+        }
+        if let Some(seeders) = self.seeders {
+            builder = builder.seeders(seeders);
+        }
+        if let Some(leechers) = self.leechers {
+            builder = builder.leechers(leechers);
+        }
+        if let Some(peers) = self.peers {
+            builder = builder.peers(peers);
+        }
+        if let Some(infohash) = self.infohash {
+            builder = builder.infohash(infohash);
+        }
+
+        return builder
+            .build()
+            .map_err(|error| CatalogError::Invalid { index, error });
+    }
+}
+
+#[async_trait]
+impl SearchBackend for Catalog {
+    /// Returns the torrents whose titles contain every word of `q` (ignoring case), or all of them if there's no `q`
+    async fn search(&self, parameters: SearchParameters) -> Result<SearchResults, TorznabError> {
+        let words: Vec<String> = parameters
+            .q
+            .unwrap_or_default()
+            .split_whitespace()
+            .map(|word| word.to_lowercase())
+            .collect();
+
+        let torrents = self
+            .torrents
+            .iter()
+            .filter(|torrent| {
+                let title = torrent.title.to_lowercase();
+                return words.iter().all(|word| title.contains(word.as_str()));
+            })
+            .cloned()
+            .collect::<Vec<Torrent>>();
+        return Ok(SearchResults::All(torrents));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{handle, ApiRequest};
+    use crate::data::{Caps, Category, Config, SearchInfo};
+
+    fn torrent(title: &str, category: u32) -> Torrent {
+        return Torrent::builder(title, 1000)
+            .category(category)
+            .magnet_uri(format!("magnet:?xt=urn:btih:{}", title.replace(' ', "")))
+            .build()
+            .unwrap();
+    }
+
+    #[actix_rt::test]
+    async fn search_by_words_category_and_page() {
+        let catalog = Catalog::new(vec![
+            torrent("Some Show S01E01 1080p", 5040),
+            torrent("Some Show S01E02 720p", 5030),
+            torrent("Some Movie 1080p", 2040),
+        ]);
+        let caps = Caps::builder()
+            .limits(100, 20)
+            .search_info(
+                SearchInfo::builder("search")
+                    .supported_param("q")
+                    .build()
+                    .unwrap(),
+            )
+            .category(
+                Category::builder(5000, "TV")
+                    .subcategory(5030, "TV/SD")
+                    .subcategory(5040, "TV/HD")
+                    .build()
+                    .unwrap(),
+            )
+            .category(
+                Category::builder(2000, "Movies")
+                    .subcategory(2040, "Movies/HD")
+                    .build()
+                    .unwrap(),
+            )
+            .build()
+            .unwrap();
+        let conf = Config::builder(caps)
+            .search(catalog)
+            .filter_categories(true)
+            .build()
+            .unwrap();
+
+        let response = handle(
+            &conf,
+            ApiRequest::from_query_string("t=search&q=some+1080P"),
+        )
+        .await;
+        assert!(response.body.contains("S01E01"));
+        assert!(response.body.contains("Some Movie"));
+        assert!(!response.body.contains("S01E02"));
+
+        let response = handle(
+            &conf,
+            ApiRequest::from_query_string("t=search&q=some&cat=5000&offset=1&limit=1"),
+        )
+        .await;
+        assert!(response
+            .body
+            .contains(r#"<newznab:response offset="1" total="2" />"#));
+        assert!(response.body.contains("S01E02"));
+        assert!(!response.body.contains("S01E01"));
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json_catalog_is_checked() {
+        let catalog = Catalog::from_json(
+            r#"[{"title": "a", "size": 1, "category_ids": [5040], "magnet_uri": "magnet:?xt=urn:btih:a", "attributes": {"seeders": 10}}]"#,
+        )
+        .unwrap();
+        assert_eq!(catalog.torrents()[0].attributes.seeders, Some(10));
+
+        let result = Catalog::from_json(r#"[{"title": "a", "size": 1, "category_ids": [5040]}]"#);
+        assert!(matches!(
+            result,
+            Err(CatalogError::Invalid { index: 0, .. })
+        ));
+    }
+
+    #[cfg(feature = "csv")]
+    #[test]
+    fn csv_catalog() {
+        let csv = "title,size,categories,magnet_uri,seeders\n\
+                   a,1,5000;5040,magnet:?xt=urn:btih:a,10\n\
+                   b,2,2000,magnet:?xt=urn:btih:b,\n";
+        let catalog = Catalog::from_csv(csv.as_bytes()).unwrap();
+        assert_eq!(catalog.torrents()[0].category_ids, vec![5000, 5040]);
+        assert_eq!(catalog.torrents()[0].attributes.seeders, Some(10));
+        assert_eq!(catalog.torrents()[1].attributes.seeders, None);
+    }
+}
@@ -0,0 +1,648 @@
+//! The standard Newznab/Torznab category tree, as typed constants
+//!
+//! Categories are numbered `xxyy`, with `xx00` being a parent category, and `xxyy` being one of its subcategories; e.g. [`TV`] is `5000`, and [`TV_HD`] is `5040`.
+//! Clients like Sonarr and Radarr expect these standard IDs, so it's best to use them (and only add your own IDs for anything that doesn't fit).
+//!
+//! Example:
+//! ```
+//! use torznab_toolkit::categories::{self, MOVIES, MOVIES_HD, TV, TV_ANIME, TV_HD};
+//! use torznab_toolkit::data::Caps;
+//!
+//! let caps = Caps::builder()
+//!     .limits(100, 50)
+//!     .categories(categories::to_categories(&[MOVIES, MOVIES_HD, TV_HD, TV_ANIME]))
+//!     .build()
+//!     .unwrap();
+//! assert_eq!(caps.categories[1].subcategories.len(), 2); // TV was added automatically, with HD and Anime
+//!
+//! // or, with all of the standard subcategories
+//! let everything = categories::with_all_subcategories(&[MOVIES, TV]);
+//! assert_eq!(everything[0].subcategories.len(), 9);
+//! ```
+use crate::data::{Category, Subcategory};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// A category (or subcategory) from the standard Newznab category tree
+///
+/// These can't be created outside of this module, so there's only the constants (and what [`by_id`] returns), and every subcategory's parent is a standard category too.
+pub struct StandardCategory {
+    /// The numeric ID of the category, e.g. `5040`
+    id: u32,
+    /// The name of the category, e.g. `HD`; for subcategories, this doesn't include the parent's name
+    name: &'static str,
+}
+
+impl StandardCategory {
+    /// Returns the numeric ID of the category, e.g. `5040`
+    pub fn id(&self) -> u32 {
+        return self.id;
+    }
+
+    /// Returns the name of the category, e.g. `HD`; for subcategories, this doesn't include the parent's name (see [`full_name`](StandardCategory::full_name))
+    pub fn name(&self) -> &'static str {
+        return self.name;
+    }
+
+    /// Returns whether this is a parent category (i.e. its ID is `xx00`), rather than a subcategory
+    #[allow(clippy::manual_is_multiple_of)] // `is_multiple_of` needs Rust 1.87
+    pub fn is_parent(&self) -> bool {
+        return self.id % 1000 == 0;
+    }
+
+    /// Returns the parent category - for a parent category, this just returns itself
+    pub fn parent(&self) -> StandardCategory {
+        let parent_id = self.id / 1000 * 1000;
+        return *ALL
+            .iter()
+            .find(|category| category.id == parent_id)
+            .expect("every standard category has a standard parent");
+    }
+
+    /// Returns all the standard subcategories of this category - empty for a subcategory
+    pub fn subcategories(&self) -> Vec<StandardCategory> {
+        if !self.is_parent() {
+            return Vec::new();
+        }
+        return ALL
+            .iter()
+            .filter(|category| !category.is_parent() && category.parent().id == self.id)
+            .copied()
+            .collect();
+    }
+
+    /// Returns the full name, e.g. `TV/HD` for [`TV_HD`], or `TV` for [`TV`]
+    pub fn full_name(&self) -> String {
+        if self.is_parent() {
+            return self.name.to_string();
+        }
+        return format!("{}/{}", self.parent().name, self.name);
+    }
+}
+
+impl From<StandardCategory> for Subcategory {
+    fn from(category: StandardCategory) -> Subcategory {
+        return Subcategory {
+            id: category.id,
+            name: category.name.to_string(),
+        };
+    }
+}
+
+/// Returns the standard category with the given ID, if there is one
+pub fn by_id(id: u32) -> Option<StandardCategory> {
+    return ALL.iter().find(|category| category.id == id).copied();
+}
+
+/// Builds a list of [`Category`]s (e.g. for [`Caps::categories`](crate::data::Caps::categories)) from a selection of standard categories
+///
+/// Selected subcategories are listed under their parent, which is added automatically if it isn't selected itself; a selected parent only gets the subcategories that were selected (see [`with_all_subcategories`] for all of them).
+/// Categories are listed in the order they (or their first subcategory) appear in the selection, and duplicates are ignored.
+pub fn to_categories(selection: &[StandardCategory]) -> Vec<Category> {
+    let mut categories: Vec<Category> = Vec::new();
+
+    for selected in selection {
+        let parent = selected.parent();
+        let index = match categories.iter().position(|c| c.id == parent.id) {
+            Some(index) => index,
+            None => {
+                categories.push(Category {
+                    id: parent.id,
+                    name: parent.name.to_string(),
+                    subcategories: Vec::new(),
+                });
+                categories.len() - 1
+            }
+        };
+
+        let subcategories = &mut categories[index].subcategories;
+        if !selected.is_parent() && !subcategories.iter().any(|s| s.id == selected.id) {
+            subcategories.push((*selected).into());
+        }
+    }
+
+    return categories;
+}
+
+/// Builds a list of [`Category`]s from the given parent categories, each with all of their standard subcategories
+///
+/// Any subcategories in `parents` are treated as their parent.
+pub fn with_all_subcategories(parents: &[StandardCategory]) -> Vec<Category> {
+    let mut selection: Vec<StandardCategory> = Vec::new();
+    for category in parents {
+        let parent = category.parent();
+        selection.push(parent);
+        selection.extend(parent.subcategories());
+    }
+    return to_categories(&selection);
+}
+
+/// `1000` - Console
+pub const CONSOLE: StandardCategory = StandardCategory {
+    id: 1000,
+    name: "Console",
+};
+/// `1010` - Console/NDS
+pub const CONSOLE_NDS: StandardCategory = StandardCategory {
+    id: 1010,
+    name: "NDS",
+};
+/// `1020` - Console/PSP
+pub const CONSOLE_PSP: StandardCategory = StandardCategory {
+    id: 1020,
+    name: "PSP",
+};
+/// `1030` - Console/Wii
+pub const CONSOLE_WII: StandardCategory = StandardCategory {
+    id: 1030,
+    name: "Wii",
+};
+/// `1040` - Console/XBox
+pub const CONSOLE_XBOX: StandardCategory = StandardCategory {
+    id: 1040,
+    name: "XBox",
+};
+/// `1050` - Console/XBox 360
+pub const CONSOLE_XBOX_360: StandardCategory = StandardCategory {
+    id: 1050,
+    name: "XBox 360",
+};
+/// `1060` - Console/Wiiware
+pub const CONSOLE_WIIWARE: StandardCategory = StandardCategory {
+    id: 1060,
+    name: "Wiiware",
+};
+/// `1070` - Console/XBox 360 DLC
+pub const CONSOLE_XBOX_360_DLC: StandardCategory = StandardCategory {
+    id: 1070,
+    name: "XBox 360 DLC",
+};
+/// `1080` - Console/PS3
+pub const CONSOLE_PS3: StandardCategory = StandardCategory {
+    id: 1080,
+    name: "PS3",
+};
+/// `1090` - Console/Other
+pub const CONSOLE_OTHER: StandardCategory = StandardCategory {
+    id: 1090,
+    name: "Other",
+};
+/// `1110` - Console/3DS
+pub const CONSOLE_3DS: StandardCategory = StandardCategory {
+    id: 1110,
+    name: "3DS",
+};
+/// `1120` - Console/PS Vita
+pub const CONSOLE_PS_VITA: StandardCategory = StandardCategory {
+    id: 1120,
+    name: "PS Vita",
+};
+/// `1130` - Console/WiiU
+pub const CONSOLE_WIIU: StandardCategory = StandardCategory {
+    id: 1130,
+    name: "WiiU",
+};
+/// `1140` - Console/XBox One
+pub const CONSOLE_XBOX_ONE: StandardCategory = StandardCategory {
+    id: 1140,
+    name: "XBox One",
+};
+/// `1180` - Console/PS4
+pub const CONSOLE_PS4: StandardCategory = StandardCategory {
+    id: 1180,
+    name: "PS4",
+};
+
+/// `2000` - Movies
+pub const MOVIES: StandardCategory = StandardCategory {
+    id: 2000,
+    name: "Movies",
+};
+/// `2010` - Movies/Foreign
+pub const MOVIES_FOREIGN: StandardCategory = StandardCategory {
+    id: 2010,
+    name: "Foreign",
+};
+/// `2020` - Movies/Other
+pub const MOVIES_OTHER: StandardCategory = StandardCategory {
+    id: 2020,
+    name: "Other",
+};
+/// `2030` - Movies/SD
+pub const MOVIES_SD: StandardCategory = StandardCategory {
+    id: 2030,
+    name: "SD",
+};
+/// `2040` - Movies/HD
+pub const MOVIES_HD: StandardCategory = StandardCategory {
+    id: 2040,
+    name: "HD",
+};
+/// `2045` - Movies/UHD
+pub const MOVIES_UHD: StandardCategory = StandardCategory {
+    id: 2045,
+    name: "UHD",
+};
+/// `2050` - Movies/BluRay
+pub const MOVIES_BLURAY: StandardCategory = StandardCategory {
+    id: 2050,
+    name: "BluRay",
+};
+/// `2060` - Movies/3D
+pub const MOVIES_3D: StandardCategory = StandardCategory {
+    id: 2060,
+    name: "3D",
+};
+/// `2070` - Movies/DVD
+pub const MOVIES_DVD: StandardCategory = StandardCategory {
+    id: 2070,
+    name: "DVD",
+};
+/// `2080` - Movies/WEB-DL
+pub const MOVIES_WEB_DL: StandardCategory = StandardCategory {
+    id: 2080,
+    name: "WEB-DL",
+};
+
+/// `3000` - Audio
+pub const AUDIO: StandardCategory = StandardCategory {
+    id: 3000,
+    name: "Audio",
+};
+/// `3010` - Audio/MP3
+pub const AUDIO_MP3: StandardCategory = StandardCategory {
+    id: 3010,
+    name: "MP3",
+};
+/// `3020` - Audio/Video
+pub const AUDIO_VIDEO: StandardCategory = StandardCategory {
+    id: 3020,
+    name: "Video",
+};
+/// `3030` - Audio/Audiobook
+pub const AUDIO_AUDIOBOOK: StandardCategory = StandardCategory {
+    id: 3030,
+    name: "Audiobook",
+};
+/// `3040` - Audio/Lossless
+pub const AUDIO_LOSSLESS: StandardCategory = StandardCategory {
+    id: 3040,
+    name: "Lossless",
+};
+/// `3050` - Audio/Other
+pub const AUDIO_OTHER: StandardCategory = StandardCategory {
+    id: 3050,
+    name: "Other",
+};
+/// `3060` - Audio/Foreign
+pub const AUDIO_FOREIGN: StandardCategory = StandardCategory {
+    id: 3060,
+    name: "Foreign",
+};
+
+/// `4000` - PC
+pub const PC: StandardCategory = StandardCategory {
+    id: 4000,
+    name: "PC",
+};
+/// `4010` - PC/0day
+pub const PC_0DAY: StandardCategory = StandardCategory {
+    id: 4010,
+    name: "0day",
+};
+/// `4020` - PC/ISO
+pub const PC_ISO: StandardCategory = StandardCategory {
+    id: 4020,
+    name: "ISO",
+};
+/// `4030` - PC/Mac
+pub const PC_MAC: StandardCategory = StandardCategory {
+    id: 4030,
+    name: "Mac",
+};
+/// `4040` - PC/Mobile-Other
+pub const PC_MOBILE_OTHER: StandardCategory = StandardCategory {
+    id: 4040,
+    name: "Mobile-Other",
+};
+/// `4050` - PC/Games
+pub const PC_GAMES: StandardCategory = StandardCategory {
+    id: 4050,
+    name: "Games",
+};
+/// `4060` - PC/Mobile-iOS
+pub const PC_MOBILE_IOS: StandardCategory = StandardCategory {
+    id: 4060,
+    name: "Mobile-iOS",
+};
+/// `4070` - PC/Mobile-Android
+pub const PC_MOBILE_ANDROID: StandardCategory = StandardCategory {
+    id: 4070,
+    name: "Mobile-Android",
+};
+
+/// `5000` - TV
+pub const TV: StandardCategory = StandardCategory {
+    id: 5000,
+    name: "TV",
+};
+/// `5010` - TV/WEB-DL
+pub const TV_WEB_DL: StandardCategory = StandardCategory {
+    id: 5010,
+    name: "WEB-DL",
+};
+/// `5020` - TV/Foreign
+pub const TV_FOREIGN: StandardCategory = StandardCategory {
+    id: 5020,
+    name: "Foreign",
+};
+/// `5030` - TV/SD
+pub const TV_SD: StandardCategory = StandardCategory {
+    id: 5030,
+    name: "SD",
+};
+/// `5040` - TV/HD
+pub const TV_HD: StandardCategory = StandardCategory {
+    id: 5040,
+    name: "HD",
+};
+/// `5045` - TV/UHD
+pub const TV_UHD: StandardCategory = StandardCategory {
+    id: 5045,
+    name: "UHD",
+};
+/// `5050` - TV/Other
+pub const TV_OTHER: StandardCategory = StandardCategory {
+    id: 5050,
+    name: "Other",
+};
+/// `5060` - TV/Sport
+pub const TV_SPORT: StandardCategory = StandardCategory {
+    id: 5060,
+    name: "Sport",
+};
+/// `5070` - TV/Anime
+pub const TV_ANIME: StandardCategory = StandardCategory {
+    id: 5070,
+    name: "Anime",
+};
+/// `5080` - TV/Documentary
+pub const TV_DOCUMENTARY: StandardCategory = StandardCategory {
+    id: 5080,
+    name: "Documentary",
+};
+
+/// `6000` - XXX
+pub const XXX: StandardCategory = StandardCategory {
+    id: 6000,
+    name: "XXX",
+};
+/// `6010` - XXX/DVD
+pub const XXX_DVD: StandardCategory = StandardCategory {
+    id: 6010,
+    name: "DVD",
+};
+/// `6020` - XXX/WMV
+pub const XXX_WMV: StandardCategory = StandardCategory {
+    id: 6020,
+    name: "WMV",
+};
+/// `6030` - XXX/XviD
+pub const XXX_XVID: StandardCategory = StandardCategory {
+    id: 6030,
+    name: "XviD",
+};
+/// `6040` - XXX/x264
+pub const XXX_X264: StandardCategory = StandardCategory {
+    id: 6040,
+    name: "x264",
+};
+/// `6045` - XXX/UHD
+pub const XXX_UHD: StandardCategory = StandardCategory {
+    id: 6045,
+    name: "UHD",
+};
+/// `6050` - XXX/Pack
+pub const XXX_PACK: StandardCategory = StandardCategory {
+    id: 6050,
+    name: "Pack",
+};
+/// `6060` - XXX/ImageSet
+pub const XXX_IMAGESET: StandardCategory = StandardCategory {
+    id: 6060,
+    name: "ImageSet",
+};
+/// `6070` - XXX/Other
+pub const XXX_OTHER: StandardCategory = StandardCategory {
+    id: 6070,
+    name: "Other",
+};
+/// `6080` - XXX/SD
+pub const XXX_SD: StandardCategory = StandardCategory {
+    id: 6080,
+    name: "SD",
+};
+/// `6090` - XXX/WEB-DL
+pub const XXX_WEB_DL: StandardCategory = StandardCategory {
+    id: 6090,
+    name: "WEB-DL",
+};
+
+/// `7000` - Books
+pub const BOOKS: StandardCategory = StandardCategory {
+    id: 7000,
+    name: "Books",
+};
+/// `7010` - Books/Mags
+pub const BOOKS_MAGS: StandardCategory = StandardCategory {
+    id: 7010,
+    name: "Mags",
+};
+/// `7020` - Books/EBook
+pub const BOOKS_EBOOK: StandardCategory = StandardCategory {
+    id: 7020,
+    name: "EBook",
+};
+/// `7030` - Books/Comics
+pub const BOOKS_COMICS: StandardCategory = StandardCategory {
+    id: 7030,
+    name: "Comics",
+};
+/// `7040` - Books/Technical
+pub const BOOKS_TECHNICAL: StandardCategory = StandardCategory {
+    id: 7040,
+    name: "Technical",
+};
+/// `7050` - Books/Other
+pub const BOOKS_OTHER: StandardCategory = StandardCategory {
+    id: 7050,
+    name: "Other",
+};
+/// `7060` - Books/Foreign
+pub const BOOKS_FOREIGN: StandardCategory = StandardCategory {
+    id: 7060,
+    name: "Foreign",
+};
+
+/// `8000` - Other
+pub const OTHER: StandardCategory = StandardCategory {
+    id: 8000,
+    name: "Other",
+};
+/// `8010` - Other/Misc
+pub const OTHER_MISC: StandardCategory = StandardCategory {
+    id: 8010,
+    name: "Misc",
+};
+/// `8020` - Other/Hashed
+pub const OTHER_HASHED: StandardCategory = StandardCategory {
+    id: 8020,
+    name: "Hashed",
+};
+
+/// Every standard category and subcategory, in order of their IDs
+pub const ALL: &[StandardCategory] = &[
+    CONSOLE,
+    CONSOLE_NDS,
+    CONSOLE_PSP,
+    CONSOLE_WII,
+    CONSOLE_XBOX,
+    CONSOLE_XBOX_360,
+    CONSOLE_WIIWARE,
+    CONSOLE_XBOX_360_DLC,
+    CONSOLE_PS3,
+    CONSOLE_OTHER,
+    CONSOLE_3DS,
+    CONSOLE_PS_VITA,
+    CONSOLE_WIIU,
+    CONSOLE_XBOX_ONE,
+    CONSOLE_PS4,
+    MOVIES,
+    MOVIES_FOREIGN,
+    MOVIES_OTHER,
+    MOVIES_SD,
+    MOVIES_HD,
+    MOVIES_UHD,
+    MOVIES_BLURAY,
+    MOVIES_3D,
+    MOVIES_DVD,
+    MOVIES_WEB_DL,
+    AUDIO,
+    AUDIO_MP3,
+    AUDIO_VIDEO,
+    AUDIO_AUDIOBOOK,
+    AUDIO_LOSSLESS,
+    AUDIO_OTHER,
+    AUDIO_FOREIGN,
+    PC,
+    PC_0DAY,
+    PC_ISO,
+    PC_MAC,
+    PC_MOBILE_OTHER,
+    PC_GAMES,
+    PC_MOBILE_IOS,
+    PC_MOBILE_ANDROID,
+    TV,
+    TV_WEB_DL,
+    TV_FOREIGN,
+    TV_SD,
+    TV_HD,
+    TV_UHD,
+    TV_OTHER,
+    TV_SPORT,
+    TV_ANIME,
+    TV_DOCUMENTARY,
+    XXX,
+    XXX_DVD,
+    XXX_WMV,
+    XXX_XVID,
+    XXX_X264,
+    XXX_UHD,
+    XXX_PACK,
+    XXX_IMAGESET,
+    XXX_OTHER,
+    XXX_SD,
+    XXX_WEB_DL,
+    BOOKS,
+    BOOKS_MAGS,
+    BOOKS_EBOOK,
+    BOOKS_COMICS,
+    BOOKS_TECHNICAL,
+    BOOKS_OTHER,
+    BOOKS_FOREIGN,
+    OTHER,
+    OTHER_MISC,
+    OTHER_HASHED,
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_category_has_a_standard_parent() {
+        for category in ALL {
+            let parent = category.parent();
+            assert!(parent.is_parent(), "{}", category.id());
+            assert_eq!(parent.id(), category.id() / 1000 * 1000);
+            assert_eq!(by_id(category.id()), Some(*category));
+            assert_eq!(
+                ALL.iter()
+                    .filter(|other| other.id() == category.id())
+                    .count(),
+                1,
+                "{} is listed more than once",
+                category.id()
+            );
+        }
+    }
+
+    #[test]
+    fn parents_and_subcategories() {
+        assert!(TV.is_parent());
+        assert!(!TV_HD.is_parent());
+        assert_eq!(TV.parent(), TV);
+        assert_eq!(TV_HD.parent(), TV);
+        assert_eq!((TV_HD.id(), TV_HD.name()), (5040, "HD"));
+        assert_eq!(TV.full_name(), "TV");
+        assert_eq!(TV_HD.full_name(), "TV/HD");
+
+        assert!(TV.subcategories().contains(&TV_ANIME));
+        assert!(TV.subcategories().iter().all(|s| s.parent() == TV));
+        assert!(TV_HD.subcategories().is_empty());
+        assert_eq!(by_id(5041), None);
+    }
+
+    #[test]
+    fn selections_to_categories() {
+        let categories = to_categories(&[TV_HD, MOVIES, TV_ANIME, TV_HD, MOVIES_HD, TV]);
+        assert_eq!(categories.len(), 2);
+        assert_eq!(
+            (categories[0].id, categories[0].name.as_str()),
+            (5000, "TV")
+        );
+        assert_eq!(
+            categories[0].subcategories,
+            vec![
+                Subcategory {
+                    id: 5040,
+                    name: "HD".to_string()
+                },
+                Subcategory {
+                    id: 5070,
+                    name: "Anime".to_string()
+                },
+            ]
+        );
+        assert_eq!(categories[1].id, 2000);
+        assert_eq!(categories[1].subcategories.len(), 1);
+
+        let everything = with_all_subcategories(&[TV_HD, MOVIES]);
+        assert_eq!(everything.len(), 2);
+        assert_eq!(everything[0].id, 5000);
+        assert_eq!(everything[0].subcategories.len(), TV.subcategories().len());
+        assert_eq!(
+            everything[1].subcategories.len(),
+            MOVIES.subcategories().len()
+        );
+    }
+}
@@ -0,0 +1,520 @@
+//! A client for other Torznab indexers: fetches their caps and search results, and parses them into [`Caps`] and [`Torrent`](crate::data::Torrent)s with [`feed`](crate::feed)
+//!
+//! The HTTP requests themselves are made by an [`HttpClient`], so any HTTP library can be used, and tests can use a mock.
+//! With the `reqwest` feature, [`reqwest::Client`](https://docs.rs/reqwest/latest/reqwest/struct.Client.html) is one; its default features are disabled, so to use HTTPS, enable one of its TLS features in your own dependency on it.
+//!
+//! A [`Client`] is also a [`SearchBackend`], so another indexer can be served (or combined with others) as if it were your own.
+//!
+//! Example:
+//! ```no_run
+//! # #[cfg(feature = "reqwest")]
+//! # async fn example() -> Result<(), torznab_toolkit::client::ClientError> {
+//! use torznab_toolkit::client::Client;
+//! use torznab_toolkit::data::SearchParameters;
+//!
+//! let client = Client::new("http://localhost:9117/api", reqwest::Client::new()).apikey("letmein");
+//! let caps = client.caps().await?;
+//!
+//! let mut parameters = SearchParameters::new("search", caps.limits.default);
+//! parameters.q = Some("totally normal torrent".to_string());
+//! for torrent in client.search(&parameters).await?.torrents {
+//!     println!("{} ({} bytes)", torrent.title, torrent.size);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use crate::data::{Caps, Episode, SearchBackend, SearchParameters, SearchResults, TorznabError};
+use crate::feed::{parse_caps, parse_feed, Feed, FeedError};
+use async_trait::async_trait;
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use std::fmt;
+use std::sync::Arc;
+use xml::reader::{EventReader, XmlEvent};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The response to an HTTP request made by an [`HttpClient`]
+pub struct HttpResponse {
+    /// The HTTP status code
+    pub status: u16,
+    /// The body, as text
+    pub body: String,
+}
+
+/// Makes the HTTP requests for a [`Client`]
+///
+/// Example, with a mock that always returns the same response:
+/// ```
+/// use async_trait::async_trait;
+/// use torznab_toolkit::client::{HttpClient, HttpResponse};
+///
+/// struct Mock(String);
+///
+/// #[async_trait]
+/// impl HttpClient for Mock {
+///     async fn get(&self, url: &str) -> Result<HttpResponse, String> {
+///         return Ok(HttpResponse { status: 200, body: self.0.clone() });
+///     }
+/// }
+/// ```
+#[async_trait]
+pub trait HttpClient: Send + Sync {
+    /// Sends a GET request to `url`, and returns the response
+    ///
+    /// This should only fail if there's no response at all (e.g. the connection was refused); error statuses are handled by the [`Client`].
+    async fn get(&self, url: &str) -> Result<HttpResponse, String>;
+}
+
+#[cfg(feature = "reqwest")]
+#[async_trait]
+impl HttpClient for reqwest::Client {
+    async fn get(&self, url: &str) -> Result<HttpResponse, String> {
+        let response = reqwest::Client::get(self, url)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        let status = response.status().as_u16();
+        let body = response.text().await.map_err(|e| e.to_string())?;
+        return Ok(HttpResponse { status, body });
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// An error from a [`Client`] request
+pub enum ClientError {
+    /// The request failed, without a response; contains the [`HttpClient`]'s error
+    Http(String),
+    /// The indexer responded with a Torznab error
+    Torznab(TorznabError),
+    /// The indexer responded with an error status, without a Torznab error
+    Status(u16),
+    /// The response couldn't be parsed
+    Feed(FeedError),
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::Http(e) => {
+                return write!(f, "request failed: {}", e);
+            }
+            ClientError::Torznab(e) => {
+                return write!(f, "Torznab error {}", e);
+            }
+            ClientError::Status(status) => {
+                return write!(f, "indexer responded with HTTP status {}", status);
+            }
+            ClientError::Feed(e) => {
+                return write!(f, "{}", e);
+            }
+        }
+    }
+}
+
+impl std::error::Error for ClientError {}
+
+impl From<FeedError> for ClientError {
+    fn from(e: FeedError) -> ClientError {
+        match e {
+            FeedError::Torznab(e) => {
+                return ClientError::Torznab(e);
+            }
+            e => {
+                return ClientError::Feed(e);
+            }
+        }
+    }
+}
+
+impl From<ClientError> for TorznabError {
+    /// Passes Torznab errors on as-is, and converts everything else to [`TorznabError::Unknown`]
+    fn from(e: ClientError) -> TorznabError {
+        match e {
+            ClientError::Torznab(e) => {
+                return e;
+            }
+            e => {
+                return TorznabError::Unknown(e.to_string());
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+/// A client for a Torznab indexer's API
+pub struct Client {
+    /// The URL of the API endpoint, without a query
+    api_url: String,
+    /// The apikey to send with searches
+    apikey: Option<String>,
+    /// Makes the requests
+    http: Arc<dyn HttpClient>,
+}
+
+impl Client {
+    /// Creates a client for the API at `api_url` (e.g. `http://localhost:8000/api`), making requests with `http`
+    pub fn new(api_url: impl AsRef<str>, http: impl HttpClient + 'static) -> Client {
+        return Client {
+            api_url: api_url.as_ref().trim_end_matches('?').to_string(),
+            apikey: None,
+            http: Arc::new(http),
+        };
+    }
+
+    /// Sets the apikey to send with searches
+    pub fn apikey(mut self, apikey: impl AsRef<str>) -> Client {
+        self.apikey = Some(apikey.as_ref().to_string());
+        return self;
+    }
+
+    /// Fetches the indexer's capabilities (`t=caps`)
+    pub async fn caps(&self) -> Result<Caps, ClientError> {
+        return Ok(parse_caps(&self.caps_xml().await?)?);
+    }
+
+    /// Searches the indexer, returning a page of results
+    ///
+    /// See [`search_xml`](Client::search_xml) for what's sent.
+    pub async fn search(&self, parameters: &SearchParameters) -> Result<Feed, ClientError> {
+        return Ok(parse_feed(&self.search_xml(parameters).await?)?);
+    }
+
+    /// Fetches the indexer's capabilities (`t=caps`), returning the XML as-is
+    pub async fn caps_xml(&self) -> Result<String, ClientError> {
+        return self.get(vec![("t", "caps".to_string())]).await;
+    }
+
+    /// Searches the indexer, returning the page of results as-is
+    ///
+    /// The search type's function (e.g. `tvsearch` for `tv-search`) and all the parameters that are set are sent, except for [`apikey`](SearchParameters::apikey) and [`principal`](SearchParameters::principal); the client's own apikey is sent instead.
+    /// An unknown search type fails with [`TorznabError::NoSuchFunction`], without making a request.
+    pub async fn search_xml(&self, parameters: &SearchParameters) -> Result<String, ClientError> {
+        return self.get(search_query(parameters)?).await;
+    }
+
+    /// Sends a request to the API with `query`, plus the apikey, and returns the body
+    async fn get(&self, mut query: Vec<(&'static str, String)>) -> Result<String, ClientError> {
+        if let Some(apikey) = &self.apikey {
+            query.push(("apikey", apikey.clone()));
+        }
+        let query: Vec<String> = query
+            .into_iter()
+            .map(|(name, value)| {
+                format!("{}={}", name, utf8_percent_encode(&value, NON_ALPHANUMERIC))
+            })
+            .collect();
+        let separator = if self.api_url.contains('?') { '&' } else { '?' };
+        let url = format!("{}{}{}", self.api_url, separator, query.join("&"));
+
+        let response = self.http.get(&url).await.map_err(ClientError::Http)?;
+        // not every indexer sends Torznab errors with an error status, and not every error status is a Torznab error (e.g. a proxy's error page)
+        if let Some(e) = torznab_error(&response.body) {
+            return Err(ClientError::Torznab(e));
+        }
+        if !(200..300).contains(&response.status) {
+            return Err(ClientError::Status(response.status));
+        }
+        return Ok(response.body);
+    }
+}
+
+impl fmt::Debug for Client {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return f
+            .debug_struct("Client")
+            .field("api_url", &self.api_url)
+            .field("apikey", &self.apikey.as_ref().map(|_| "<apikey>"))
+            .finish_non_exhaustive();
+    }
+}
+
+#[async_trait]
+impl SearchBackend for Client {
+    /// Searches the indexer with [`Client::search`], returning its page of results along with its total
+    async fn search(&self, parameters: SearchParameters) -> Result<SearchResults, TorznabError> {
+        let offset = parameters.offset.unwrap_or(0) as u64;
+        let feed = Client::search(self, &parameters).await?;
+        let total = feed.total.unwrap_or(offset + feed.torrents.len() as u64);
+        return Ok(SearchResults::Paged {
+            torrents: feed.torrents,
+            total,
+        });
+    }
+}
+
+/// Returns the error in `body` if it's a Torznab error response (`<error code="..." description="..."/>`)
+fn torznab_error(body: &str) -> Option<TorznabError> {
+    for event in EventReader::new(body.as_bytes()) {
+        match event {
+            Ok(XmlEvent::StartElement {
+                name, attributes, ..
+            }) => {
+                // only the root element matters
+                if name.local_name != "error" {
+                    return None;
+                }
+                let attr = |name: &str| {
+                    attributes
+                        .iter()
+                        .find(|attribute| attribute.name.local_name == name)
+                        .map(|attribute| attribute.value.as_str())
+                };
+                let code = attr("code")?.trim().parse().ok()?;
+                return Some(TorznabError::from_code(
+                    code,
+                    attr("description").unwrap_or_default(),
+                ));
+            }
+            Ok(_) => {}
+            Err(_) => {
+                return None;
+            }
+        }
+    }
+    return None;
+}
+
+/// Returns the query for a search, without the apikey
+fn search_query(parameters: &SearchParameters) -> Result<Vec<(&'static str, String)>, ClientError> {
+    let function = match parameters.search_type.as_str() {
+        "search" => "search",
+        "tv-search" => "tvsearch",
+        "movie-search" => "movie",
+        "audio-search" => "music",
+        "book-search" => "book",
+        _ => {
+            return Err(ClientError::Torznab(TorznabError::NoSuchFunction));
+        }
+    };
+
+    let mut query: Vec<(&'static str, String)> = vec![("t", function.to_string())];
+    let mut add = |name: &'static str, value: Option<String>| {
+        if let Some(value) = value {
+            query.push((name, value));
+        }
+    };
+
+    add("q", parameters.q.clone());
+    add(
+        "cat",
+        parameters.categories.as_ref().map(|ids| {
+            ids.iter()
+                .map(|id| id.to_string())
+                .collect::<Vec<String>>()
+                .join(",")
+        }),
+    );
+    add(
+        "attrs",
+        parameters.attributes.as_ref().map(|names| names.join(",")),
+    );
+    if parameters.extended_attrs == Some(true) {
+        add("extended", Some("1".to_string()));
+    }
+    add("offset", parameters.offset.map(|v| v.to_string()));
+    add("limit", Some(parameters.limit.to_string()));
+
+    if let Some(tv) = &parameters.tv {
+        add("season", tv.season.map(|v| v.to_string()));
+        add(
+            "ep",
+            tv.episode.map(|episode| match episode {
+                Episode::Number(number) | Episode::Absolute(number) => number.to_string(),
+                Episode::Daily { month, day } => format!("{:02}/{:02}", month, day),
+            }),
+        );
+        add("tvdbid", tv.tvdb_id.map(|v| v.to_string()));
+        add("rid", tv.tvrage_id.map(|v| v.to_string()));
+        add("tvmazeid", tv.tvmaze_id.map(|v| v.to_string()));
+        add("imdbid", tv.imdb_id.clone());
+        add("tmdbid", tv.tmdb_id.map(|v| v.to_string()));
+        add("traktid", tv.trakt_id.map(|v| v.to_string()));
+    }
+    if let Some(movie) = &parameters.movie {
+        add("imdbid", movie.imdb_id.clone());
+        add("tmdbid", movie.tmdb_id.map(|v| v.to_string()));
+        add("year", movie.year.map(|v| v.to_string()));
+        add("genre", movie.genre.clone());
+    }
+    if let Some(music) = &parameters.music {
+        add("artist", music.artist.clone());
+        add("album", music.album.clone());
+        add("label", music.label.clone());
+        add("track", music.track.clone());
+        add("year", music.year.map(|v| v.to_string()));
+    }
+    if let Some(book) = &parameters.book {
+        add("author", book.author.clone());
+        add("title", book.title.clone());
+        add("publisher", book.publisher.clone());
+    }
+
+    return Ok(query);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{handle, ApiRequest};
+    use crate::data::{Config, TvSearchParameters};
+    use crate::dummy::create_empty_config;
+
+    /// Serves requests with [`api::handle`](crate::api::handle) directly, recording the URLs
+    struct MockServer {
+        conf: Config,
+        urls: std::sync::Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl HttpClient for Arc<MockServer> {
+        async fn get(&self, url: &str) -> Result<HttpResponse, String> {
+            self.urls.lock().unwrap().push(url.to_string());
+            let (_, query) = url.split_once('?').unwrap_or_default();
+            let response = handle(&self.conf, ApiRequest::from_query_string(query)).await;
+            return Ok(HttpResponse {
+                status: response.status,
+                body: response.body,
+            });
+        }
+    }
+
+    fn mock() -> Arc<MockServer> {
+        return Arc::new(MockServer {
+            conf: create_empty_config(),
+            urls: Default::default(),
+        });
+    }
+
+    #[actix_rt::test]
+    async fn caps_are_fetched() {
+        let server = mock();
+        let client = Client::new("http://localhost/api?", server.clone());
+        let xml = client.caps_xml().await.unwrap();
+        assert_eq!(
+            server.urls.lock().unwrap()[0],
+            "http://localhost/api?t=caps"
+        );
+        assert!(xml.contains("<caps>"));
+    }
+
+    #[actix_rt::test]
+    async fn caps_and_search_results_are_parsed() {
+        let client = Client::new("http://localhost/api", mock()).apikey("letmein");
+        assert_eq!(client.caps().await.unwrap(), create_empty_config().caps);
+
+        let mut parameters = SearchParameters::new("search", 10);
+        parameters.extended_attrs = Some(true);
+        let feed = client.search(&parameters).await.unwrap();
+        assert_eq!(feed.total, Some(1));
+        assert_eq!(feed.torrents[0].title, "totally normal torrent");
+        assert_eq!(feed.torrents[0].attributes.seeders, Some(10));
+
+        match SearchBackend::search(&client, parameters).await.unwrap() {
+            SearchResults::Paged { torrents, total } => {
+                assert_eq!(torrents, feed.torrents);
+                assert_eq!(total, 1);
+            }
+            SearchResults::All(_) => panic!("expected a page of results"),
+        }
+    }
+
+    #[actix_rt::test]
+    async fn search_sends_parameters() {
+        let server = mock();
+        let client = Client::new("http://localhost/api", server.clone()).apikey("letmein");
+
+        let mut parameters = SearchParameters::new("search", 10);
+        parameters.q = Some("totally normal".to_string());
+        parameters.extended_attrs = Some(true);
+        let xml = client.search_xml(&parameters).await.unwrap();
+        assert_eq!(
+            server.urls.lock().unwrap()[0],
+            "http://localhost/api?t=search&q=totally%20normal&extended=1&limit=10&apikey=letmein"
+        );
+        assert!(xml.contains("totally normal torrent"));
+
+        let mut parameters = SearchParameters::new("tv-search", 10);
+        parameters.tv = Some(TvSearchParameters {
+            season: Some(2024),
+            episode: Some(Episode::Daily { month: 3, day: 5 }),
+            ..Default::default()
+        });
+        let _ = client.search_xml(&parameters).await;
+        assert_eq!(
+            server.urls.lock().unwrap()[1],
+            "http://localhost/api?t=tvsearch&limit=10&season=2024&ep=03%2F05&apikey=letmein"
+        );
+    }
+
+    #[actix_rt::test]
+    async fn errors() {
+        // the mock server accepts any apikey, but requires one
+        let client = Client::new("http://localhost/api", mock());
+        let parameters = SearchParameters::new("search", 10);
+        assert_eq!(
+            client.search_xml(&parameters).await,
+            Err(ClientError::Torznab(TorznabError::IncorrectCredentials))
+        );
+
+        let parameters = SearchParameters::new("nonsense", 10);
+        assert_eq!(
+            client.search_xml(&parameters).await,
+            Err(ClientError::Torznab(TorznabError::NoSuchFunction))
+        );
+
+        struct Responds(u16, &'static str);
+        #[async_trait]
+        impl HttpClient for Responds {
+            async fn get(&self, _url: &str) -> Result<HttpResponse, String> {
+                return Ok(HttpResponse {
+                    status: self.0,
+                    body: self.1.to_string(),
+                });
+            }
+        }
+        let client = Client::new(
+            "http://localhost/api",
+            Responds(502, "<html>Bad Gateway</html>"),
+        );
+        assert_eq!(client.caps_xml().await, Err(ClientError::Status(502)));
+
+        // some indexers send errors with a 200
+        let client = Client::new(
+            "http://localhost/api",
+            Responds(
+                200,
+                r#"<?xml version="1.0"?><error code="201" description="Incorrect parameter: limit"/>"#,
+            ),
+        );
+        assert_eq!(
+            client.caps_xml().await,
+            Err(ClientError::Torznab(TorznabError::IncorrectParameter(
+                "limit".to_string()
+            )))
+        );
+
+        let client = Client::new("http://localhost/api", Responds(200, "<rss/>"));
+        assert!(matches!(
+            client.search(&SearchParameters::new("search", 10)).await,
+            Err(ClientError::Feed(FeedError::Invalid(_)))
+        ));
+    }
+
+    #[cfg(all(feature = "reqwest", feature = "rocket"))]
+    #[actix_rt::test]
+    async fn reqwest_against_local_server() {
+        let options = crate::server::ServerOptions {
+            port: Some(0),
+            ..Default::default()
+        };
+        let server = crate::spawn(create_empty_config(), options).await.unwrap();
+        let url = format!("http://{}/api", server.local_addr().unwrap());
+
+        let client = Client::new(url, reqwest::Client::new()).apikey("letmein");
+        assert_eq!(client.caps().await.unwrap(), create_empty_config().caps);
+        let mut parameters = SearchParameters::new("search", 10);
+        parameters.q = Some("normal".to_string());
+        assert_eq!(client.search(&parameters).await.unwrap().torrents.len(), 1);
+
+        server.shutdown().await.unwrap();
+    }
+}
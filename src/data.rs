@@ -1,19 +1,485 @@
 //! Contains tons of structs used by the library
 //!
 //! All examples here are based off the [Torznab spec](https://torznab.github.io/spec-1.3-draft/torznab/Specification-v1.3.html)'s `/api?caps` example.
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-pub(crate) type AuthFunc = fn(String) -> Result<bool, String>;
-pub(crate) type SearchFunc = fn(SearchParameters) -> Result<Vec<Torrent>, String>;
+use std::fmt;
+use std::net::IpAddr;
+use std::sync::Arc;
+/// A plain auth function, usable as an [`Authenticator`] through its blanket implementation
+///
+/// Returning `true` indicates that the apikey is valid, and `false` rejects the request as an incorrect apikey; an error is sent to the client as [`TorznabError::Unknown`]
+pub type AuthFunc = fn(String) -> Result<bool, String>;
+/// A plain search function, usable as a [`SearchBackend`] through its blanket implementation
+pub type SearchFunc = fn(SearchParameters) -> Result<Vec<Torrent>, String>;
+
+/// A backend that handles search queries
+///
+/// This is what actually does the searching; it's given the [`SearchParameters`] for a query, and returns the matching torrents in the order they should be listed; see [`SearchResults`] for how paging works.
+/// Unlike a plain [`SearchFunc`], a backend can hold its own state (e.g. a database pool or an HTTP client) and `.await` things while searching.
+///
+/// Any `Fn(SearchParameters) -> Result<Vec<Torrent>, String>` (including plain `fn`s and closures) is already a backend, so those can be used directly; their errors are returned to the client as [`TorznabError::Unknown`].
+///
+/// Example:
+/// ```
+/// use async_trait::async_trait;
+/// use torznab_toolkit::data::{SearchBackend, SearchParameters, SearchResults, Torrent, TorznabError};
+///
+/// struct MyIndexer {
+///     torrents: Vec<Torrent>,
+/// }
+///
+/// #[async_trait]
+/// impl SearchBackend for MyIndexer {
+///     async fn search(&self, parameters: SearchParameters) -> Result<SearchResults, TorznabError> {
+///         let q = parameters.q.unwrap_or_default();
+///         let torrents: Vec<Torrent> = self
+///             .torrents
+///             .iter()
+///             .filter(|torrent| torrent.title.contains(&q))
+///             .cloned()
+///             .collect();
+///         return Ok(torrents.into()); // torznab-toolkit applies `offset` and `limit`
+///     }
+/// }
+/// ```
+#[async_trait]
+pub trait SearchBackend: Send + Sync {
+    /// Searches for torrents matching `parameters`
+    ///
+    /// The search type is specified in `parameters.search_type`
+    ///
+    /// If this returns an error, it's sent to the client as a Torznab error response
+    async fn search(&self, parameters: SearchParameters) -> Result<SearchResults, TorznabError>;
+}
+
+#[async_trait]
+impl<F> SearchBackend for F
+where
+    F: Fn(SearchParameters) -> Result<Vec<Torrent>, String> + Send + Sync,
+{
+    async fn search(&self, parameters: SearchParameters) -> Result<SearchResults, TorznabError> {
+        return Ok(SearchResults::All(self(parameters)?));
+    }
+}
+
+#[derive(Clone, Default)]
+/// A [`SearchBackend`] that hands each search to the backend registered for its search type, e.g. one for `tv-search` and another for `movie-search`
+///
+/// Searches of types without a backend go to the fallback backend, if there is one; otherwise, they fail with [`TorznabError::FunctionNotAvailable`].
+/// Usually it's easier to use [`ConfigBuilder::search_handler`](crate::builder::ConfigBuilder::search_handler), which sets this up and fills in [`Caps::searching`] from the registered handlers, so they can't get out of sync.
+///
+/// Example:
+/// ```
+/// use torznab_toolkit::data::{SearchInfo, SearchParameters, SearchRouter, Torrent};
+///
+/// fn tv_search(parameters: SearchParameters) -> Result<Vec<Torrent>, String> {
+///     let season = parameters.tv.and_then(|tv| tv.season);
+///     return Ok(vec![/* ... */]);
+/// }
+///
+/// let router = SearchRouter::new().handler(
+///     SearchInfo::builder("tv-search").supported_params(["q", "season", "ep"]).build().unwrap(),
+///     tv_search,
+/// );
+/// assert_eq!(router.search_infos()[0].supported_params, vec!["q", "season", "ep"]);
+/// ```
+pub struct SearchRouter {
+    /// The search types and their backends
+    handlers: Vec<(SearchInfo, Arc<dyn SearchBackend>)>,
+    /// The backend for search types without their own
+    fallback: Option<Arc<dyn SearchBackend>>,
+}
+
+impl SearchRouter {
+    /// Creates a router with no backends
+    pub fn new() -> SearchRouter {
+        return SearchRouter::default();
+    }
+
+    /// Registers the backend for `search_info`'s search type, replacing any that was already registered for it
+    ///
+    /// `search_info` is what's listed in the caps for it (see [`SearchRouter::search_infos`]).
+    pub fn handler(
+        mut self,
+        search_info: SearchInfo,
+        backend: impl SearchBackend + 'static,
+    ) -> SearchRouter {
+        self.handlers
+            .retain(|(info, _)| info.search_type != search_info.search_type);
+        self.handlers.push((search_info, Arc::new(backend)));
+        return self;
+    }
+
+    /// Sets the backend for search types that don't have their own
+    pub fn fallback(mut self, backend: impl SearchBackend + 'static) -> SearchRouter {
+        self.fallback = Some(Arc::new(backend));
+        return self;
+    }
+
+    /// Sets the backend for search types that don't have their own, from an already shared backend
+    pub(crate) fn shared_fallback(mut self, backend: Arc<dyn SearchBackend>) -> SearchRouter {
+        self.fallback = Some(backend);
+        return self;
+    }
+
+    /// Returns the [`SearchInfo`]s of the registered backends, in the order they were registered
+    pub fn search_infos(&self) -> Vec<SearchInfo> {
+        return self.handlers.iter().map(|(info, _)| info.clone()).collect();
+    }
+}
+
+#[async_trait]
+impl SearchBackend for SearchRouter {
+    async fn search(&self, parameters: SearchParameters) -> Result<SearchResults, TorznabError> {
+        let handler = self
+            .handlers
+            .iter()
+            .find(|(info, _)| info.search_type == parameters.search_type);
+
+        match (handler, &self.fallback) {
+            (Some((_, backend)), _) => {
+                return backend.search(parameters).await;
+            }
+            (None, Some(fallback)) => {
+                return fallback.search(parameters).await;
+            }
+            (None, None) => {
+                return Err(TorznabError::FunctionNotAvailable);
+            }
+        }
+    }
+}
 
-// TODO: Redo this all so that is uses builders with `AsRef<str>` arguments instead
+impl fmt::Debug for SearchRouter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SearchRouter")
+            .field("search_infos", &self.search_infos())
+            .field(
+                "fallback",
+                &self.fallback.as_ref().map(|_| "<search backend>"),
+            )
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// The results of a search, as returned by a [`SearchBackend`]
+///
+/// Either way, the total number of results is reported to the client (`<newznab:response offset="..." total="..."/>`), which clients like Sonarr use for paging.
+pub enum SearchResults {
+    /// *All* the matching torrents; torznab-toolkit applies `offset` and `limit` from the [`SearchParameters`] itself
+    ///
+    /// A `Vec<Torrent>` can be converted to this with `.into()`
+    All(Vec<Torrent>),
+    /// Torrents that were already paged by the backend (i.e. `offset` and `limit` were already applied), along with the total number of matching torrents
+    Paged {
+        /// The torrents in the requested page
+        torrents: Vec<Torrent>,
+        /// The total number of matching torrents, across all pages
+        total: u64,
+    },
+}
+
+impl From<Vec<Torrent>> for SearchResults {
+    fn from(torrents: Vec<Torrent>) -> SearchResults {
+        return SearchResults::All(torrents);
+    }
+}
+
+/// Decides whether a request is allowed, and who it's from
+///
+/// It's given the apikey (if one was specified) along with some info about the request ([`RequestContext`]), and returns the [`Principal`] the request is from, or `None` if it isn't authorized.
+/// Returning `None` responds with [`TorznabError::IncorrectCredentials`]; for anything else, like suspended accounts, return the relevant [`TorznabError`].
+/// The principal is then passed on to the search backend in [`SearchParameters::principal`], so backends can behave differently per user.
+///
+/// Any `Fn(String) -> Result<bool, String>` (like [`AuthFunc`]) is already an authenticator; requests without an apikey are rejected, as are ones it returns `false` for, its errors are returned as [`TorznabError::Unknown`], and the principal's `id` is the apikey.
+///
+/// Example:
+/// ```
+/// use async_trait::async_trait;
+/// use std::collections::HashMap;
+/// use torznab_toolkit::data::{Authenticator, Principal, RequestContext, TorznabError};
+///
+/// struct KeyStore {
+///     users_by_key: HashMap<String, String>,
+/// }
+///
+/// #[async_trait]
+/// impl Authenticator for KeyStore {
+///     async fn authenticate(
+///         &self,
+///         apikey: Option<String>,
+///         _context: &RequestContext,
+///     ) -> Result<Option<Principal>, TorznabError> {
+///         let user = apikey.and_then(|key| self.users_by_key.get(&key).cloned());
+///         return Ok(user.map(Principal::new));
+///     }
+/// }
+/// ```
+#[async_trait]
+pub trait Authenticator: Send + Sync {
+    /// Authenticates a request, returning who it's from if it's allowed, or `None` if it isn't
+    async fn authenticate(
+        &self,
+        apikey: Option<String>,
+        context: &RequestContext,
+    ) -> Result<Option<Principal>, TorznabError>;
+}
+
+#[async_trait]
+impl<F> Authenticator for F
+where
+    F: Fn(String) -> Result<bool, String> + Send + Sync,
+{
+    async fn authenticate(
+        &self,
+        apikey: Option<String>,
+        _context: &RequestContext,
+    ) -> Result<Option<Principal>, TorznabError> {
+        match apikey {
+            Some(apikey) => match self(apikey.clone()) {
+                Ok(true) => {
+                    return Ok(Some(Principal::new(apikey)));
+                }
+                Ok(false) => {
+                    return Ok(None);
+                }
+                Err(e) => {
+                    return Err(TorznabError::Unknown(e));
+                }
+            },
+            None => {
+                return Ok(None);
+            }
+        }
+    }
+}
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+/// An error returned to the client as a Torznab error response, i.e. `<error code="..." description="..."/>`
+///
+/// The codes and their meanings are from the [Torznab spec](https://torznab.github.io/spec-1.3-draft/torznab/Specification-v1.3.html#error-codes); the HTTP status code of the response is from [`TorznabError::http_status`].
+///
+/// Example:
+/// ```
+/// # use torznab_toolkit::data::TorznabError;
+/// let error = TorznabError::IncorrectParameter("limit".to_string());
+/// assert_eq!(error.code(), 201);
+/// assert_eq!(error.description(), "Incorrect parameter: limit");
+/// ```
+pub enum TorznabError {
+    /// `100` - Incorrect user credentials
+    IncorrectCredentials,
+    /// `101` - Account suspended
+    AccountSuspended,
+    /// `102` - Insufficient privileges/not authorized
+    InsufficientPrivileges,
+    /// `200` - Missing parameter; contains the name of the parameter
+    MissingParameter(String),
+    /// `201` - Incorrect parameter; contains the name of the parameter
+    IncorrectParameter(String),
+    /// `202` - No such function (i.e. not defined in the spec)
+    NoSuchFunction,
+    /// `203` - Function not available (i.e. an optional function that isn't implemented)
+    FunctionNotAvailable,
+    /// `300` - No such item
+    NoSuchItem,
+    /// `500` - Request limit reached
+    RequestLimitReached,
+    /// `501` - Download limit reached
+    DownloadLimitReached,
+    /// `900` - Unknown error; contains a description of the error
+    Unknown(String),
+    /// `910` - API disabled
+    ApiDisabled,
+    /// Any other error code, with its description
+    Other {
+        /// The numeric error code
+        code: u32,
+        /// The description of the error
+        description: String,
+    },
+}
+
+impl TorznabError {
+    /// Returns the numeric Torznab error code
+    pub fn code(&self) -> u32 {
+        return match self {
+            TorznabError::IncorrectCredentials => 100,
+            TorznabError::AccountSuspended => 101,
+            TorznabError::InsufficientPrivileges => 102,
+            TorznabError::MissingParameter(_) => 200,
+            TorznabError::IncorrectParameter(_) => 201,
+            TorznabError::NoSuchFunction => 202,
+            TorznabError::FunctionNotAvailable => 203,
+            TorznabError::NoSuchItem => 300,
+            TorznabError::RequestLimitReached => 500,
+            TorznabError::DownloadLimitReached => 501,
+            TorznabError::Unknown(_) => 900,
+            TorznabError::ApiDisabled => 910,
+            TorznabError::Other { code, .. } => *code,
+        };
+    }
+
+    /// Returns the description of the error, as sent to the client
+    pub fn description(&self) -> String {
+        return match self {
+            TorznabError::IncorrectCredentials => "Incorrect user credentials".to_string(),
+            TorznabError::AccountSuspended => "Account suspended".to_string(),
+            TorznabError::InsufficientPrivileges => {
+                "Insufficient privileges/not authorized".to_string()
+            }
+            TorznabError::MissingParameter(name) => format!("Missing parameter: {}", name),
+            TorznabError::IncorrectParameter(name) => format!("Incorrect parameter: {}", name),
+            TorznabError::NoSuchFunction => "No such function".to_string(),
+            TorznabError::FunctionNotAvailable => "Function not available".to_string(),
+            TorznabError::NoSuchItem => "No such item".to_string(),
+            TorznabError::RequestLimitReached => "Request limit reached".to_string(),
+            TorznabError::DownloadLimitReached => "Download limit reached".to_string(),
+            TorznabError::Unknown(description) => description.clone(),
+            TorznabError::ApiDisabled => "API disabled".to_string(),
+            TorznabError::Other { description, .. } => description.clone(),
+        };
+    }
+
+    /// Creates the error for a code and description, e.g. from an `<error code="..." description="..."/>` response; the inverse of [`code`](TorznabError::code) and [`description`](TorznabError::description)
+    ///
+    /// Codes that aren't in the spec are returned as [`TorznabError::Other`], as are `200` and `201` if the description doesn't name the parameter like this crate does.
+    pub fn from_code(code: u32, description: impl AsRef<str>) -> TorznabError {
+        let description = description.as_ref();
+        return match code {
+            100 => TorznabError::IncorrectCredentials,
+            101 => TorznabError::AccountSuspended,
+            102 => TorznabError::InsufficientPrivileges,
+            200 if description.starts_with("Missing parameter: ") => {
+                TorznabError::MissingParameter(
+                    description
+                        .trim_start_matches("Missing parameter: ")
+                        .to_string(),
+                )
+            }
+            201 if description.starts_with("Incorrect parameter: ") => {
+                TorznabError::IncorrectParameter(
+                    description
+                        .trim_start_matches("Incorrect parameter: ")
+                        .to_string(),
+                )
+            }
+            202 => TorznabError::NoSuchFunction,
+            203 => TorznabError::FunctionNotAvailable,
+            300 => TorznabError::NoSuchItem,
+            500 => TorznabError::RequestLimitReached,
+            501 => TorznabError::DownloadLimitReached,
+            900 => TorznabError::Unknown(description.to_string()),
+            910 => TorznabError::ApiDisabled,
+            _ => TorznabError::Other {
+                code,
+                description: description.to_string(),
+            },
+        };
+    }
+
+    /// Returns the HTTP status code the error response is sent with
+    ///
+    /// The spec doesn't specify any HTTP status codes, so these are just the closest matches; clients like Sonarr go off the error code in the body anyways.
+    pub fn http_status(&self) -> u16 {
+        return match self.code() {
+            100 => 401,
+            101..=199 => 403,
+            200..=299 => 400,
+            300..=399 => 404,
+            500 | 501 => 429,
+            910 => 503,
+            _ => 500,
+        };
+    }
+}
+
+impl fmt::Display for TorznabError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "{} - {}", self.code(), self.description());
+    }
+}
+
+impl std::error::Error for TorznabError {}
+
+impl From<String> for TorznabError {
+    /// Converts a plain error message to [`TorznabError::Unknown`]
+    fn from(description: String) -> TorznabError {
+        return TorznabError::Unknown(description);
+    }
+}
+
+impl From<&str> for TorznabError {
+    /// Converts a plain error message to [`TorznabError::Unknown`]
+    fn from(description: &str) -> TorznabError {
+        return TorznabError::Unknown(description.to_string());
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Info about the request being authenticated, for use by an [`Authenticator`]
+pub struct RequestContext {
+    /// The IP address of the client, if known
+    pub client_ip: Option<IpAddr>,
+    /// The request's headers, as `(name, value)` pairs, in the order they were received
+    pub headers: Vec<(String, String)>,
+    /// What type of search this is
+    ///
+    /// Search types: `search`, `tv-search`, `movie-search`, `audio-search`, `book-search`
+    pub search_type: String,
+}
+
+impl RequestContext {
+    /// Returns the value of the first header named `name` (case-insensitive), if there is one
+    pub fn header(&self, name: impl AsRef<str>) -> Option<&str> {
+        let name = name.as_ref();
+        return self
+            .headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str());
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The identity of an authenticated client, as returned by an [`Authenticator`]
+pub struct Principal {
+    /// An identifier for the client, e.g. a username or account ID
+    pub id: String,
+    /// Any other info about the client that the search backend might need
+    pub attributes: HashMap<String, String>,
+}
+
+impl Principal {
+    /// Creates a principal with the given `id` and no attributes
+    pub fn new(id: impl AsRef<str>) -> Principal {
+        return Principal {
+            id: id.as_ref().to_string(),
+            attributes: HashMap::new(),
+        };
+    }
+}
+
+/// The known search types, as used in [`SearchInfo::search_type`] and [`SearchParameters::search_type`]
+pub const SEARCH_TYPES: [&str; 5] = [
+    "search",
+    "tv-search",
+    "movie-search",
+    "audio-search",
+    "book-search",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 /// The maximum and defaults for the `limit` parameter in queries
 /// `max` is the maximum number of results the program can return
 /// `default` is the default number of results the program will return
 ///
 /// Example:
 /// ```
+/// # use torznab_toolkit::data::Limits;
 /// let query_limits = Limits {
 ///     max: 100, // maximum of 100 results per search query
 ///     default: 50,  // default of 50
@@ -31,34 +497,45 @@ pub struct Limits {
     pub default: u32,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 /// A struct holding the info for a type of search
 ///
 /// Example:
 /// ```
+/// # use torznab_toolkit::data::SearchInfo;
 /// let tv_query_search_info = SearchInfo {
 ///     search_type: "tv-search".to_string(),
 ///     available: true,
 ///     supported_params: vec!["q", "rid", "tvdbid", "season", "ep"]
-///     .into_iter().map(|i| i.to_string()).collect::<String>(), // this bit's just to make all the `str`s to `String`s
+///     .into_iter().map(|i| i.to_string()).collect::<Vec<String>>(), // this bit's just to make all the `str`s to `String`s
 /// };
 /// ```
 pub struct SearchInfo {
     /// What type of search this is - must be `search`, `tv-search`, `movie-search`, `audio-search`, or `book-search`
     pub search_type: String,
     /// Whether this search type is available
+    ///
+    /// Defaults to `true` when deserialized
+    #[serde(default = "available_by_default")]
     pub available: bool,
     /// The supported parameters for this search type
     ///
     /// Highly recommended: `q` (free text query)
+    #[serde(default)]
     pub supported_params: Vec<String>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// The default for [`SearchInfo::available`] when deserializing
+fn available_by_default() -> bool {
+    return true;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 /// Contains subcategories, for use in [`Category`]
 ///
 /// Example:
 /// ```
+/// # use torznab_toolkit::data::Subcategory;
 /// let subcat = Subcategory {
 ///     id: 2010,
 ///     name: "Foreign".to_string(),
@@ -73,15 +550,17 @@ pub struct Subcategory {
     pub name: String,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 /// Contains a category, for use in [`Caps`] and searches as a query parameter
 ///
 /// Example, using `subcat` from the [`Subcategory`] example:
 /// ```
+/// # use torznab_toolkit::data::{Category, Subcategory};
+/// # let subcat = Subcategory { id: 2010, name: "Foreign".to_string() };
 /// let category = Category {
 ///     id: 2000,
 ///     name: "Movies".to_string(),
-///     subcategory: vec![subcat],
+///     subcategories: vec![subcat],
 /// };
 /// ```
 pub struct Category {
@@ -92,14 +571,16 @@ pub struct Category {
     /// The name of the category, e.g. "Movies"
     pub name: String,
     /// A vector of all the subcategory in this category
+    #[serde(default)]
     pub subcategories: Vec<Subcategory>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 /// Contains a genre, for use in [`Caps`] and searches as a query parameter
 ///
 /// Example:
 /// ```
+/// # use torznab_toolkit::data::Genre;
 /// let genre = Genre {
 ///     id: 1,
 ///     category_id: 5000,
@@ -117,12 +598,13 @@ pub struct Genre {
     pub name: String,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 /// Contains a tag, for use in [`Caps`] and searches as a query parameter
 ///
 /// Example:
 ///
 /// ```
+/// # use torznab_toolkit::data::Tag;
 /// let tag = Tag {
 ///     name: "trusted".to_string(),
 ///     description: "Uploader has high reputation".to_string(),
@@ -135,7 +617,7 @@ pub struct Tag {
     pub description: String,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 /// Holds the configuration for the capabilities of the Torznab server (used in `/api?t=caps`)
 ///
 /// <div class="warning">Note that this library might not support all the capabilities listed in yet, so check the README before listing capabilities, or just accept that unsupported capabilities will return error 501.
@@ -144,6 +626,13 @@ pub struct Tag {
 ///
 /// Example, using other examples:
 /// ```
+/// # use std::collections::HashMap;
+/// # use torznab_toolkit::data::*;
+/// # let query_limits = Limits { max: 100, default: 50 };
+/// # let tv_query_search_info = SearchInfo { search_type: "tv-search".to_string(), available: true, supported_params: vec!["q".to_string()] };
+/// # let category = Category { id: 2000, name: "Movies".to_string(), subcategories: vec![] };
+/// # let genre = Genre { id: 1, category_id: 5000, name: "Kids".to_string() };
+/// # let tag = Tag { name: "trusted".to_string(), description: "Uploader has high reputation".to_string() };
 /// let mut info: HashMap<String, String> = HashMap::new();
 /// info.insert("version".to_string(), "1.1".to_string());
 ///
@@ -164,6 +653,9 @@ pub struct Caps {
     /// The max and default number of items to be returned by queries
     pub limits: Limits,
     /// Info about each type of search
+    ///
+    /// Can be left out when deserializing, e.g. if it's filled in by [`ConfigBuilder::search_handler`](crate::builder::ConfigBuilder::search_handler)
+    #[serde(default)]
     pub searching: Vec<SearchInfo>,
     /// What categories the server has
     pub categories: Vec<Category>,
@@ -173,42 +665,112 @@ pub struct Caps {
     pub tags: Option<Vec<Tag>>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+impl Caps {
+    /// Expands a list of category IDs (e.g. from [`SearchParameters::categories`]) to include the subcategories of any parent categories in it
+    ///
+    /// E.g. if the TV category (`5000`) has the subcategories `5030` and `5040`, then `[5000, 2040]` is expanded to `[5000, 5030, 5040, 2040]`.
+    /// IDs that aren't in `categories` are kept as-is.
+    pub fn expand_category_ids(&self, ids: &[u32]) -> Vec<u32> {
+        let mut expanded: Vec<u32> = Vec::new();
+        for id in ids {
+            if !expanded.contains(id) {
+                expanded.push(*id);
+            }
+            if let Some(category) = self.categories.iter().find(|c| c.id == *id) {
+                for subcategory in &category.subcategories {
+                    if !expanded.contains(&subcategory.id) {
+                        expanded.push(subcategory.id);
+                    }
+                }
+            }
+        }
+        return expanded;
+    }
+
+    /// Returns the [`SearchInfo`] for `search_type` (e.g. `tv-search`), if it's listed in `searching`
+    pub fn search_info(&self, search_type: &str) -> Option<&SearchInfo> {
+        return self
+            .searching
+            .iter()
+            .find(|info| info.search_type == search_type);
+    }
+}
+
+#[derive(Clone)]
 /// A struct that holds configuration for torznab-toolkit
-/// The search function (`/api?t=search`) and capabilities (`/api?t=caps` - struct [`Caps`]) are required
+/// The search backend (`/api?t=search`) and capabilities (`/api?t=caps` - struct [`Caps`]) are required
 /// Everything else is optional
 ///
 /// Example, using other examples:
 /// ```
+/// # use std::sync::Arc;
+/// # use torznab_toolkit::data::*;
+/// # let caps_data = Caps { server_info: None, limits: Limits { max: 100, default: 50 }, searching: vec![], categories: vec![], genres: None, tags: None };
 /// fn search_func(parameters: SearchParameters) -> Result<Vec<Torrent>, String> {
-///     let torrent = /* see `Torrent` example */
-///     return torrent;
+///     return Ok(vec![/* see `Torrent` example */]);
 /// }
 ///
 /// fn auth_func(apikey: String) -> Result<bool, String> {
-///     if apikey == "letmein".to_string() {    
+///     if apikey == "letmein".to_string() {
 ///         return Ok(true);
 ///     }
 ///     return Ok(false);
 /// }
 ///
 /// let conf = Config {
-///     search: search,
-///     auth: Some(auth),
+///     search: Arc::new(search_func),
+///     auth: Some(Arc::new(auth_func)),
 ///     caps: caps_data,
-/// }
+///     filter_categories: false,
+///     unsupported_params: UnsupportedParams::Allow,
+/// };
 /// ```
 pub struct Config {
-    /// The function to use for all search types
+    /// The backend to use for all search types; see [`SearchBackend`]
+    ///
+    /// To use a different backend for each search type, use a [`SearchRouter`] (or [`ConfigBuilder::search_handler`](crate::builder::ConfigBuilder::search_handler)).
     ///
     /// What search types are available is dependent on what's marked as available in the `searching` field of `caps` ([`Caps`])
     ///
     /// Search types: `search`, `tv-search`, `movie-search`, `audio-search`, `book-search`
-    pub search: SearchFunc,
-    /// The auth function - if not specified, then no authorization is needed.
-    pub auth: Option<AuthFunc>,
+    pub search: Arc<dyn SearchBackend>,
+    /// The authenticator (or auth function); see [`Authenticator`] - if not specified, then no authorization is needed.
+    pub auth: Option<Arc<dyn Authenticator>>,
     /// The capabilities of the indexer
     pub caps: Caps,
+    /// Whether torznab-toolkit should filter out any torrents that aren't in the requested categories (`cat`)
+    ///
+    /// Requested parent categories include all of their subcategories listed in `caps` (see [`Caps::expand_category_ids`]), so backends don't need to do that themselves.
+    /// For [`SearchResults::Paged`] results, only the returned page can be filtered, so `total` is left as-is; paging backends should filter by category themselves.
+    pub filter_categories: bool,
+    /// What to do with search parameters that aren't in the search type's `supported_params` in [`Caps`]; see [`UnsupportedParams`]
+    pub unsupported_params: UnsupportedParams,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+/// What to do with search parameters (e.g. `q`, `season`, `imdbid`) that aren't listed in the search type's [`SearchInfo::supported_params`]
+///
+/// Only the query parameters of searches are affected; the general ones (`apikey`, `cat`, `attrs`, `extended`, `offset`, and `limit`) are always allowed, and empty values are treated as missing.
+pub enum UnsupportedParams {
+    /// Pass them on to the backend anyway (the default)
+    #[default]
+    Allow,
+    /// Remove them from the query before it's parsed, so the backend never sees them
+    Strip,
+    /// Reject the request with [`TorznabError::IncorrectParameter`] (`201`)
+    Reject,
+}
+
+impl fmt::Debug for Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Config")
+            .field("search", &"<search backend>")
+            .field("auth", &self.auth.as_ref().map(|_| "<authenticator>"))
+            .field("caps", &self.caps)
+            .field("filter_categories", &self.filter_categories)
+            .field("unsupported_params", &self.unsupported_params)
+            .finish()
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -229,15 +791,132 @@ pub struct SearchParameters {
     /// Whether *all* extended attributes should be included in the search results; overrules `attributes`
     pub extended_attrs: Option<bool>,
     /// How many items to skip/offset by in the results.
+    ///
+    /// Only needs to be handled by the backend if it returns [`SearchResults::Paged`]
     pub offset: Option<u32>,
     /// The maximum number of items to return - also limited to whatever `limits` is in [`Caps`]
+    ///
+    /// Only needs to be handled by the backend if it returns [`SearchResults::Paged`]
     pub limit: u32,
+    /// Who the request is from, as returned by the [`Authenticator`] - `None` if no authenticator is configured
+    pub principal: Option<Principal>,
+    /// The TV-specific parameters - only present for `tv-search`
+    pub tv: Option<TvSearchParameters>,
+    /// The movie-specific parameters - only present for `movie-search`
+    pub movie: Option<MovieSearchParameters>,
+    /// The music-specific parameters - only present for `audio-search`
+    pub music: Option<MusicSearchParameters>,
+    /// The book-specific parameters - only present for `book-search`
+    pub book: Option<BookSearchParameters>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+impl SearchParameters {
+    /// Creates the parameters for a search of type `search_type` (e.g. `tv-search`) returning up to `limit` items, with everything else unset
+    ///
+    /// Mostly useful for sending searches with the [`client`](crate::client), or calling backends directly.
+    pub fn new(search_type: impl AsRef<str>, limit: u32) -> SearchParameters {
+        return SearchParameters {
+            search_type: search_type.as_ref().to_string(),
+            q: None,
+            apikey: None,
+            categories: None,
+            attributes: None,
+            extended_attrs: None,
+            offset: None,
+            limit,
+            principal: None,
+            tv: None,
+            movie: None,
+            music: None,
+            book: None,
+        };
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+/// Holds the TV-specific parameters of a `tv-search` query (`/api?t=tvsearch`)
+///
+/// All of these are optional, and clients like Sonarr will only send the ones listed in `supported_params` for `tv-search` in [`Caps`]
+pub struct TvSearchParameters {
+    /// The season number (`season`)
+    ///
+    /// For daily shows, this is the year instead, e.g. `2024`
+    pub season: Option<u32>,
+    /// The episode (`ep`); see [`Episode`]
+    pub episode: Option<Episode>,
+    /// The TheTVDB ID of the show (`tvdbid`)
+    pub tvdb_id: Option<u32>,
+    /// The TVRage ID of the show (`rid`)
+    pub tvrage_id: Option<u32>,
+    /// The TVmaze ID of the show (`tvmazeid`)
+    pub tvmaze_id: Option<u32>,
+    /// The IMDb ID of the show (`imdbid`), as given by the client, e.g. `tt0944947`
+    pub imdb_id: Option<String>,
+    /// The TMDb ID of the show (`tmdbid`)
+    pub tmdb_id: Option<u32>,
+    /// The Trakt ID of the show (`traktid`)
+    pub trakt_id: Option<u32>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+/// Holds the movie-specific parameters of a `movie-search` query (`/api?t=movie`)
+pub struct MovieSearchParameters {
+    /// The IMDb ID of the movie (`imdbid`), as given by the client, e.g. `tt0111161`
+    pub imdb_id: Option<String>,
+    /// The TMDb ID of the movie (`tmdbid`)
+    pub tmdb_id: Option<u32>,
+    /// The year the movie was released (`year`)
+    pub year: Option<u32>,
+    /// The genre of the movie (`genre`), e.g. `Comedy`
+    pub genre: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+/// Holds the music-specific parameters of an `audio-search` query (`/api?t=music`)
+pub struct MusicSearchParameters {
+    /// The name of the artist (`artist`)
+    pub artist: Option<String>,
+    /// The name of the album (`album`)
+    pub album: Option<String>,
+    /// The name of the record label (`label`)
+    pub label: Option<String>,
+    /// The name of the track (`track`)
+    pub track: Option<String>,
+    /// The year the album was released (`year`)
+    pub year: Option<u32>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+/// Holds the book-specific parameters of a `book-search` query (`/api?t=book`)
+pub struct BookSearchParameters {
+    /// The name of the author (`author`)
+    pub author: Option<String>,
+    /// The title of the book (`title`)
+    pub title: Option<String>,
+    /// The name of the publisher (`publisher`)
+    pub publisher: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// An episode, as specified by the `ep` parameter of a TV search
+pub enum Episode {
+    /// A regular episode number within the season, e.g. `ep=5` with `season=2`
+    Number(u32),
+    /// A daily show's episode, specified as `MM/DD` (e.g. `ep=03/15` with `season=2024`); the year is in `season`
+    Daily {
+        /// The month, from 1 to 12
+        month: u8,
+        /// The day of the month, from 1 to 31
+        day: u8,
+    },
+    /// An absolute episode number, as used for anime, i.e. `ep` without a `season`
+    Absolute(u32),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 /// Holds the info for a torrent
 ///
-/// Any attributes not listed here are optional, and can be put in `other_attributes`; **however**, the following are recommended:
+/// Any attributes not listed here are optional, and can be put in `attributes` ([`TorrentAttributes`]); **however**, the following are recommended:
 /// - `seeders`
 /// - `leechers`
 /// - `peers`
@@ -245,8 +924,10 @@ pub struct SearchParameters {
 /// - `link` (link to a webpage; if not specified, will fallback to `torrent_file_url`, then `magnet_uri`)
 ///
 /// <div class="warning">One of either `torrent_file_url` or `magnet_uri` are required.</div>
+///
 /// Example:
 /// ```
+/// # use torznab_toolkit::data::{Torrent, TorrentAttributes};
 /// let torrent = Torrent {
 ///     title: "totally normal torrent".to_string(),
 ///     description: None,
@@ -254,6 +935,13 @@ pub struct SearchParameters {
 ///     category_ids: vec![1010],
 ///     torrent_file_url: Some("http://localhost/totally-normal.torrent".to_string()),
 ///     magnet_uri: Some("magnet:?xt=urn:btih:blahblahblahdothechachacha".to_string()),
+///     link: None,
+///     pub_date: None,
+///     attributes: TorrentAttributes {
+///         seeders: Some(10),
+///         leechers: Some(2),
+///         ..Default::default()
+///     },
 ///     other_attributes: None,
 /// };
 /// ```
@@ -270,6 +958,215 @@ pub struct Torrent {
     pub torrent_file_url: Option<String>,
     /// The magnet URI o the torrent
     pub magnet_uri: Option<String>,
-    /// Any other attributes
+    /// A link to a webpage for the torrent - if not specified, falls back to `torrent_file_url`, then `magnet_uri`
+    pub link: Option<String>,
+    /// When the torrent was published, as an RFC 2822 date (e.g. `Tue, 10 Jun 2003 04:00:00 GMT`) - optional
+    pub pub_date: Option<String>,
+    /// The spec's predefined attributes, like `seeders` and `infohash`; see [`TorrentAttributes`]
+    #[serde(default)]
+    pub attributes: TorrentAttributes,
+    /// Any other (custom) attributes, not covered by `attributes`
+    ///
+    /// These are listed as `<torznab:attr name="..." value="..."/>`, just like the predefined ones
     pub other_attributes: Option<HashMap<String, String>>,
 }
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+/// The predefined Torznab attributes of a torrent; all of them are optional
+///
+/// Each one is listed as `<torznab:attr name="..." value="..."/>`, with the name in its docs.
+/// See the [Torznab spec](https://torznab.github.io/spec-1.3-draft/torznab/Specification-v1.3.html#predefined-attributes) for details on each.
+pub struct TorrentAttributes {
+    /// Number of seeders (`seeders`)
+    pub seeders: Option<u32>,
+    /// Number of leechers (`leechers`)
+    pub leechers: Option<u32>,
+    /// Number of peers, i.e. seeders and leechers (`peers`)
+    pub peers: Option<u32>,
+    /// The torrent's infohash (`infohash`)
+    pub infohash: Option<String>,
+    /// Number of times the torrent has been downloaded (`grabs`)
+    pub grabs: Option<u32>,
+    /// Number of files in the torrent (`files`)
+    pub files: Option<u32>,
+    /// Who uploaded the torrent (`poster`)
+    pub poster: Option<String>,
+    /// The release group (`team`)
+    pub team: Option<String>,
+    /// The factor downloads count towards the user's ratio, e.g. `0` for freeleech (`downloadvolumefactor`)
+    pub download_volume_factor: Option<f64>,
+    /// The factor uploads count towards the user's ratio (`uploadvolumefactor`)
+    pub upload_volume_factor: Option<f64>,
+    /// The minimum ratio the torrent needs to be seeded to (`minimumratio`)
+    pub minimum_ratio: Option<f64>,
+    /// The minimum time the torrent needs to be seeded for, in seconds (`minimumseedtime`)
+    pub minimum_seed_time: Option<u64>,
+    /// The TheTVDB ID (`tvdbid`)
+    pub tvdb_id: Option<u32>,
+    /// The TVRage ID (`rageid`)
+    pub tvrage_id: Option<u32>,
+    /// The TVmaze ID (`tvmazeid`)
+    pub tvmaze_id: Option<u32>,
+    /// The IMDb ID, e.g. `tt0944947`
+    ///
+    /// This is listed both as `imdbid` (with the `tt`) and `imdb` (just the digits), since clients look for either
+    pub imdb_id: Option<String>,
+    /// The TMDb ID (`tmdbid`)
+    pub tmdb_id: Option<u32>,
+    /// The Trakt ID (`traktid`)
+    pub trakt_id: Option<u32>,
+    /// The season number, or year for daily shows (`season`)
+    pub season: Option<u32>,
+    /// The episode number (`episode`)
+    pub episode: Option<u32>,
+    /// The genre(s), comma-separated (`genre`)
+    pub genre: Option<String>,
+    /// The release year (`year`)
+    pub year: Option<u32>,
+    /// A URL to a cover image (`coverurl`)
+    pub cover_url: Option<String>,
+    /// A URL to a backdrop image (`backdropurl`)
+    pub backdrop_url: Option<String>,
+    /// The artist (`artist`)
+    pub artist: Option<String>,
+    /// The album (`album`)
+    pub album: Option<String>,
+    /// The record label (`label`)
+    pub label: Option<String>,
+    /// The track(s) (`track`)
+    pub track: Option<String>,
+    /// The author (`author`)
+    pub author: Option<String>,
+    /// The title of the book (`booktitle`)
+    pub book_title: Option<String>,
+    /// The publisher (`publisher`)
+    pub publisher: Option<String>,
+}
+
+impl TorrentAttributes {
+    /// Returns the attributes that are set, as `(name, value)` pairs, using their Torznab names
+    pub fn to_pairs(&self) -> Vec<(&'static str, String)> {
+        let mut pairs: Vec<(&'static str, String)> = Vec::new();
+        let mut add = |name: &'static str, value: Option<String>| {
+            if let Some(value) = value {
+                pairs.push((name, value));
+            }
+        };
+
+        add("seeders", self.seeders.map(|v| v.to_string()));
+        add("leechers", self.leechers.map(|v| v.to_string()));
+        add("peers", self.peers.map(|v| v.to_string()));
+        add("infohash", self.infohash.clone());
+        add("grabs", self.grabs.map(|v| v.to_string()));
+        add("files", self.files.map(|v| v.to_string()));
+        add("poster", self.poster.clone());
+        add("team", self.team.clone());
+        add(
+            "downloadvolumefactor",
+            self.download_volume_factor.map(|v| v.to_string()),
+        );
+        add(
+            "uploadvolumefactor",
+            self.upload_volume_factor.map(|v| v.to_string()),
+        );
+        add("minimumratio", self.minimum_ratio.map(|v| v.to_string()));
+        add(
+            "minimumseedtime",
+            self.minimum_seed_time.map(|v| v.to_string()),
+        );
+        add("tvdbid", self.tvdb_id.map(|v| v.to_string()));
+        add("rageid", self.tvrage_id.map(|v| v.to_string()));
+        add("tvmazeid", self.tvmaze_id.map(|v| v.to_string()));
+        add(
+            "imdb",
+            self.imdb_id
+                .as_ref()
+                .map(|id| id.trim_start_matches("tt").to_string()),
+        );
+        add("imdbid", self.imdb_id.clone());
+        add("tmdbid", self.tmdb_id.map(|v| v.to_string()));
+        add("traktid", self.trakt_id.map(|v| v.to_string()));
+        add("season", self.season.map(|v| v.to_string()));
+        add("episode", self.episode.map(|v| v.to_string()));
+        add("genre", self.genre.clone());
+        add("year", self.year.map(|v| v.to_string()));
+        add("coverurl", self.cover_url.clone());
+        add("backdropurl", self.backdrop_url.clone());
+        add("artist", self.artist.clone());
+        add("album", self.album.clone());
+        add("label", self.label.clone());
+        add("track", self.track.clone());
+        add("author", self.author.clone());
+        add("booktitle", self.book_title.clone());
+        add("publisher", self.publisher.clone());
+
+        return pairs;
+    }
+
+    /// Sets an attribute by its Torznab name (e.g. `seeders`), from its value as listed in a feed; the inverse of [`to_pairs`](TorrentAttributes::to_pairs)
+    ///
+    /// `imdb` (just the digits) only sets [`imdb_id`](TorrentAttributes::imdb_id) if `imdbid` hasn't already.
+    /// Returns `false` if `name` isn't a predefined attribute, or if `value` isn't valid for it (e.g. a non-numeric `seeders`), so it can be kept as a custom attribute instead.
+    pub fn set(&mut self, name: &str, value: &str) -> bool {
+        fn number<T: std::str::FromStr>(field: &mut Option<T>, value: &str) -> bool {
+            match value.trim().parse() {
+                Ok(value) => {
+                    *field = Some(value);
+                    return true;
+                }
+                Err(_) => {
+                    return false;
+                }
+            }
+        }
+        fn text(field: &mut Option<String>, value: &str) -> bool {
+            *field = Some(value.to_string());
+            return true;
+        }
+
+        return match name {
+            "seeders" => number(&mut self.seeders, value),
+            "leechers" => number(&mut self.leechers, value),
+            "peers" => number(&mut self.peers, value),
+            "infohash" => text(&mut self.infohash, value),
+            "grabs" => number(&mut self.grabs, value),
+            "files" => number(&mut self.files, value),
+            "poster" => text(&mut self.poster, value),
+            "team" => text(&mut self.team, value),
+            "downloadvolumefactor" => number(&mut self.download_volume_factor, value),
+            "uploadvolumefactor" => number(&mut self.upload_volume_factor, value),
+            "minimumratio" => number(&mut self.minimum_ratio, value),
+            "minimumseedtime" => number(&mut self.minimum_seed_time, value),
+            "tvdbid" => number(&mut self.tvdb_id, value),
+            "rageid" => number(&mut self.tvrage_id, value),
+            "tvmazeid" => number(&mut self.tvmaze_id, value),
+            "imdb" => {
+                let digits = value.trim();
+                if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+                    return false;
+                }
+                if self.imdb_id.is_none() {
+                    self.imdb_id = Some(format!("tt{:0>7}", digits));
+                }
+                true
+            }
+            "imdbid" => text(&mut self.imdb_id, value),
+            "tmdbid" => number(&mut self.tmdb_id, value),
+            "traktid" => number(&mut self.trakt_id, value),
+            "season" => number(&mut self.season, value),
+            "episode" => number(&mut self.episode, value),
+            "genre" => text(&mut self.genre, value),
+            "year" => number(&mut self.year, value),
+            "coverurl" => text(&mut self.cover_url, value),
+            "backdropurl" => text(&mut self.backdrop_url, value),
+            "artist" => text(&mut self.artist, value),
+            "album" => text(&mut self.album, value),
+            "label" => text(&mut self.label, value),
+            "track" => text(&mut self.track, value),
+            "author" => text(&mut self.author, value),
+            "booktitle" => text(&mut self.book_title, value),
+            "publisher" => text(&mut self.publisher, value),
+            _ => false,
+        };
+    }
+}
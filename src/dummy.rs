@@ -1,17 +1,17 @@
 //! Some dummy stuff for testing the API
 use crate::data::*;
-use std::collections::HashMap;
+#[cfg(any(feature = "rocket", feature = "axum"))]
+use std::io::{Read, Write};
 
 fn dummy_search_func(_a: SearchParameters) -> Result<Vec<Torrent>, String> {
-    return Ok(vec![Torrent {
-        title: "totally normal torrent".to_string(),
-        description: None,
-        size: 9872349573,
-        category_ids: vec![1010],
-        torrent_file_url: Some("http://localhost/totally-normal.torrent".to_string()),
-        magnet_uri: Some("magnet:?xt=urn:btih:blahblahblahdothechachacha".to_string()),
-        other_attributes: None,
-    }]);
+    let torrent = Torrent::builder("totally normal torrent", 9872349573)
+        .category(1010)
+        .torrent_file_url("http://localhost/totally-normal.torrent")
+        .magnet_uri("magnet:?xt=urn:btih:blahblahblahdothechachacha")
+        .seeders(10)
+        .build()
+        .map_err(|e| e.to_string())?;
+    return Ok(vec![torrent]);
 }
 
 fn dummy_auth_func(_a: String) -> Result<bool, String> {
@@ -20,62 +20,220 @@ fn dummy_auth_func(_a: String) -> Result<bool, String> {
 
 /// Creates a bare-minimum config
 pub(crate) fn create_empty_config() -> Config {
-    let searching = vec![SearchInfo {
-        search_type: "search".to_string(),
-        available: true,
-        supported_params: vec!["q".to_string()],
-    }];
-
-    let subcategories = vec![Subcategory {
-        id: 1010,
-        name: "b".to_string(),
-    }];
-
-    let categories = vec![Category {
-        id: 1000,
-        name: "a".to_string(),
-        subcategories: subcategories,
-    }];
-
-    let genres = vec![Genre {
-        id: 1,
-        category_id: 1000,
-        name: "c".to_string(),
-    }];
-
-    let tags = vec![Tag {
-        name: "a".to_string(),
-        description: "b".to_string(),
-    }];
-
-    let mut server_info: HashMap<String, String> = HashMap::new();
-    server_info.insert("title".to_string(), "Test Torznab server".to_string());
-    server_info.insert("email".to_string(), "test@example.com".to_string());
-    server_info.insert("version".to_string(), "1.0".to_string());
-
-    return Config {
-        search: dummy_search_func,
-        auth: Some(dummy_auth_func),
-        caps: Caps {
-            server_info: Some(server_info),
-            limits: Limits {
-                max: 100,
-                default: 20,
-            },
-            searching: searching,
-            categories: categories,
-            genres: Some(genres),
-            tags: Some(tags),
-        },
-    };
+    let caps = Caps::builder()
+        .server_info("title", "Test Torznab server")
+        .server_info("email", "test@example.com")
+        .server_info("version", "1.0")
+        .limits(100, 20)
+        .search_info(
+            SearchInfo::builder("search")
+                .supported_param("q")
+                .build()
+                .unwrap(),
+        )
+        .category(
+            Category::builder(1000, "a")
+                .subcategory(1010, "b")
+                .build()
+                .unwrap(),
+        )
+        .genre(1, 1000, "c")
+        .tag("a", "b")
+        .build()
+        .unwrap();
+
+    return Config::builder(caps)
+        .search(dummy_search_func)
+        .auth(dummy_auth_func)
+        .build()
+        .unwrap();
+}
+
+/// Sends a bare-bones HTTP/1.0 `GET` request for `path` over `stream`, and returns the whole response (headers included)
+///
+/// This blocks, so it needs to be run with `tokio::task::spawn_blocking` when the server's on the same runtime.
+#[cfg(any(feature = "rocket", feature = "axum"))]
+pub(crate) fn http_get(mut stream: impl Read + Write, path: &str) -> String {
+    write!(stream, "GET {} HTTP/1.0\r\nHost: localhost\r\n\r\n", path).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    return response;
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "rocket"))]
 mod tests {
-    use crate::{dummy::create_empty_config, run};
+    use crate::dummy::{create_empty_config, http_get};
+    use crate::server::ServerOptions;
+    use crate::{attach, build, build_with_options, spawn, RunError};
+    use rocket::http::Status;
+    use rocket::local::asynchronous::Client;
+    use std::net::{IpAddr, Ipv4Addr, TcpStream};
 
     #[actix_rt::test]
     async fn api_with_empty_config() {
-        run(create_empty_config()).await.unwrap();
+        let options = ServerOptions {
+            port: Some(0),
+            ..Default::default()
+        };
+        let server = spawn(create_empty_config(), options).await.unwrap();
+        let address = server.local_addr().unwrap();
+        assert_ne!(address.port(), 0);
+
+        let response = tokio::task::spawn_blocking(move || {
+            http_get(TcpStream::connect(address).unwrap(), "/api?t=caps")
+        })
+        .await
+        .unwrap();
+        assert!(response.starts_with("HTTP/1.0 200 OK"));
+        assert!(response.contains("<caps>"));
+
+        server.shutdown().await.unwrap();
+        assert!(TcpStream::connect(address).is_err());
+    }
+
+    #[actix_rt::test]
+    async fn search_lists_torznab_attributes() {
+        let rocket = build(create_empty_config(), "/api").unwrap();
+        let client = Client::tracked(rocket).await.unwrap();
+
+        let body = client
+            .get("/api?t=search&q=normal&apikey=letmein&extended=1")
+            .dispatch()
+            .await
+            .into_string()
+            .await
+            .unwrap();
+
+        assert!(body.contains(r#"<torznab:attr name="size" value="9872349573" />"#));
+        assert!(body.contains(r#"<torznab:attr name="category" value="1010" />"#));
+        assert!(body.contains(r#"<torznab:attr name="seeders" value="10" />"#));
+        assert!(body.contains(r#"<newznab:response offset="0" total="1" />"#));
+    }
+
+    #[actix_rt::test]
+    async fn search_lists_only_requested_attributes() {
+        let rocket = build(create_empty_config(), "/api").unwrap();
+        let client = Client::tracked(rocket).await.unwrap();
+
+        let body = client
+            .get("/api?t=search&q=normal&apikey=letmein")
+            .dispatch()
+            .await
+            .into_string()
+            .await
+            .unwrap();
+        assert!(body.contains(r#"<torznab:attr name="size" value="9872349573" />"#));
+        assert!(!body.contains(r#"name="seeders""#));
+        assert!(!body.contains(r#"name="magneturl""#));
+
+        let body = client
+            .get("/api?t=search&q=normal&apikey=letmein&attrs=seeders")
+            .dispatch()
+            .await
+            .into_string()
+            .await
+            .unwrap();
+        assert!(body.contains(r#"<torznab:attr name="seeders" value="10" />"#));
+        assert!(!body.contains(r#"name="magneturl""#));
+    }
+
+    #[actix_rt::test]
+    async fn search_filters_by_expanded_categories() {
+        let mut conf = create_empty_config();
+        conf.filter_categories = true;
+        let rocket = build(conf, "/api").unwrap();
+        let client = Client::tracked(rocket).await.unwrap();
+
+        // the dummy torrent is in 1010, a subcategory of 1000
+        let body = client
+            .get("/api?t=search&apikey=letmein&cat=1000")
+            .dispatch()
+            .await
+            .into_string()
+            .await
+            .unwrap();
+        assert!(body.contains(r#"<newznab:response offset="0" total="1" />"#));
+
+        let body = client
+            .get("/api?t=search&apikey=letmein&cat=2000,1020")
+            .dispatch()
+            .await
+            .into_string()
+            .await
+            .unwrap();
+        assert!(body.contains(r#"<newznab:response offset="0" total="0" />"#));
+    }
+
+    #[rocket::get("/")]
+    fn index() -> &'static str {
+        return "hello";
+    }
+
+    #[actix_rt::test]
+    async fn api_attached_at_custom_base_path() {
+        let rocket = rocket::build().mount("/", rocket::routes![index]);
+        let rocket = attach(rocket, "/torznab/api/", create_empty_config()).unwrap();
+        let client = Client::tracked(rocket).await.unwrap();
+
+        let response = client.get("/").dispatch().await;
+        assert_eq!(response.into_string().await.unwrap(), "hello");
+
+        let response = client.get("/torznab/api?t=caps").dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        assert!(response.into_string().await.unwrap().contains("<caps>"));
+
+        let response = client.get("/api?t=caps").dispatch().await;
+        assert_eq!(response.status(), Status::NotFound);
+    }
+
+    #[test]
+    fn server_options_override_rocket_config() {
+        let options = ServerOptions {
+            address: Some(IpAddr::V4(Ipv4Addr::UNSPECIFIED)),
+            port: Some(0),
+            ..Default::default()
+        };
+        let rocket = build_with_options(create_empty_config(), "/api", &options).unwrap();
+        let config: rocket::Config = rocket.figment().extract().unwrap();
+        assert_eq!(config.address, IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+        assert_eq!(config.port, 0);
+
+        let options = ServerOptions {
+            unix_socket: Some("/tmp/torznab.sock".into()),
+            ..Default::default()
+        };
+        assert!(matches!(
+            build_with_options(create_empty_config(), "/api", &options),
+            Err(RunError::UnsupportedOption(_))
+        ));
+    }
+
+    #[actix_rt::test]
+    async fn unknown_functions_are_torznab_errors() {
+        let rocket = build(create_empty_config(), "/api").unwrap();
+        let client = Client::tracked(rocket).await.unwrap();
+
+        let response = client.get("/api?t=nope").dispatch().await;
+        assert_eq!(response.status(), Status::BadRequest);
+        assert!(response
+            .into_string()
+            .await
+            .unwrap()
+            .contains(r#"<error code="202" description="No such function" />"#));
+
+        let response = client.get("/api/nope").dispatch().await;
+        assert_eq!(response.status(), Status::BadRequest);
+        assert!(response
+            .into_string()
+            .await
+            .unwrap()
+            .contains(r#"code="202""#));
+
+        let response = client.get("/api?t=movie&apikey=letmein").dispatch().await;
+        assert!(response
+            .into_string()
+            .await
+            .unwrap()
+            .contains(r#"code="203""#));
     }
 }
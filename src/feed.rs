@@ -0,0 +1,604 @@
+//! Parsing Torznab (and Newznab) responses: search feeds into [`Torrent`]s, and caps into [`Caps`]
+//!
+//! This is the reverse of what [`api::handle`](crate::api::handle) writes, and is what the [`client`](crate::client) uses to read other indexers' responses.
+//! It doesn't do any HTTP itself, so it can also be used on saved feeds, e.g. to process them offline:
+//! ```no_run
+//! # fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let xml = std::fs::read_to_string("feed.xml")?;
+//! for torrent in torznab_toolkit::feed::parse_torrents(&xml)? {
+//!     println!("{}: {:?}", torrent.title, torrent.attributes.seeders);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Attributes are read from both `torznab:attr` and `newznab:attr` elements, going by their namespaces rather than their prefixes.
+//! Error responses (`<error code="..." description="..."/>`) are returned as [`FeedError::Torznab`].
+use crate::data::{
+    Caps, Category, Genre, Limits, SearchInfo, Subcategory, Tag, Torrent, TorznabError,
+};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use xml::reader::{EventReader, XmlEvent};
+
+/// The namespace of `torznab:attr` elements
+const TORZNAB_NAMESPACE: &str = "http://torznab.com/schemas/2015/feed";
+/// The namespace of `newznab:attr` elements
+const NEWZNAB_NAMESPACE: &str = "http://www.newznab.com/DTD/2010/feeds/attributes/";
+
+#[derive(Debug, Clone, PartialEq)]
+/// An error from parsing a Torznab response
+pub enum FeedError {
+    /// The response isn't well-formed XML; contains the parser's error
+    Xml(String),
+    /// The response is a Torznab error
+    Torznab(TorznabError),
+    /// The response is XML, but not what was expected (e.g. a missing `<channel>`, or a non-numeric size); contains what's wrong
+    Invalid(String),
+}
+
+impl fmt::Display for FeedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FeedError::Xml(e) => {
+                return write!(f, "invalid XML: {}", e);
+            }
+            FeedError::Torznab(e) => {
+                return write!(f, "Torznab error {}", e);
+            }
+            FeedError::Invalid(e) => {
+                return write!(f, "invalid response: {}", e);
+            }
+        }
+    }
+}
+
+impl std::error::Error for FeedError {}
+
+#[derive(Debug, Clone, PartialEq)]
+/// A parsed search feed (the response to `t=search`, `tvsearch`, etc.)
+pub struct Feed {
+    /// The title of the feed, usually the indexer's name
+    pub title: Option<String>,
+    /// The offset of this page of results, from `<newznab:response offset="..."/>`
+    pub offset: Option<u64>,
+    /// The total number of results across all pages, from `<newznab:response total="..."/>`
+    pub total: Option<u64>,
+    /// The torrents in this page of results
+    pub torrents: Vec<Torrent>,
+}
+
+/// Parses a search feed
+///
+/// Each item's enclosure becomes its [`torrent_file_url`](Torrent::torrent_file_url) or, for magnet links, its [`magnet_uri`](Torrent::magnet_uri), and each `torznab:attr`/`newznab:attr` is put in [`attributes`](Torrent::attributes) or, if it's not a predefined one, [`other_attributes`](Torrent::other_attributes).
+/// The size comes from the `size` attribute, falling back to a `<size>` element, then the enclosure's `length` (unless it's `0`).
+/// The item's `link` is left out if it's the same as the .torrent URL or magnet URI, since that's just the fallback.
+pub fn parse_feed(xml: &str) -> Result<Feed, FeedError> {
+    let root = parse_document(xml)?;
+    if root.name != "rss" {
+        return Err(FeedError::Invalid(format!(
+            "expected `<rss>`, found `<{}>`",
+            root.name
+        )));
+    }
+    let channel = root
+        .child("channel")
+        .ok_or_else(|| FeedError::Invalid("missing `<channel>`".to_string()))?;
+
+    let mut feed = Feed {
+        title: channel.child_text("title"),
+        offset: None,
+        total: None,
+        torrents: Vec::new(),
+    };
+    if let Some(response) = channel.child("response") {
+        feed.offset = optional_number(response, "offset")?;
+        feed.total = optional_number(response, "total")?;
+    }
+    for item in channel.children("item") {
+        feed.torrents.push(parse_item(item)?);
+    }
+    return Ok(feed);
+}
+
+/// Parses a search feed like [`parse_feed`], returning just the torrents
+pub fn parse_torrents(xml: &str) -> Result<Vec<Torrent>, FeedError> {
+    return Ok(parse_feed(xml)?.torrents);
+}
+
+/// Parses a caps response (`t=caps`)
+///
+/// `<server>`'s attributes become [`Caps::server_info`] (`None` if it has none), and `<genres>` and `<tags>` are `None` if they're not listed.
+pub fn parse_caps(xml: &str) -> Result<Caps, FeedError> {
+    let root = parse_document(xml)?;
+    if root.name != "caps" {
+        return Err(FeedError::Invalid(format!(
+            "expected `<caps>`, found `<{}>`",
+            root.name
+        )));
+    }
+
+    let server_info = root
+        .child("server")
+        .map(|server| server.attributes.clone())
+        .filter(|attributes| !attributes.is_empty());
+
+    let limits = root
+        .child("limits")
+        .ok_or_else(|| FeedError::Invalid("missing `<limits>`".to_string()))?;
+    let limits = Limits {
+        max: number(limits, "max")?,
+        default: number(limits, "default")?,
+    };
+
+    let mut searching = Vec::new();
+    if let Some(element) = root.child("searching") {
+        for search in &element.children {
+            searching.push(SearchInfo {
+                search_type: search.name.clone(),
+                available: search.attr("available") == Some("yes"),
+                supported_params: search
+                    .attr("supportedParams")
+                    .unwrap_or_default()
+                    .split(',')
+                    .map(|param| param.trim().to_string())
+                    .filter(|param| !param.is_empty())
+                    .collect(),
+            });
+        }
+    }
+
+    let mut categories = Vec::new();
+    if let Some(element) = root.child("categories") {
+        for category in element.children("category") {
+            let mut subcategories = Vec::new();
+            for subcategory in category.children("subcat") {
+                subcategories.push(Subcategory {
+                    id: number(subcategory, "id")?,
+                    name: subcategory.attr("name").unwrap_or_default().to_string(),
+                });
+            }
+            categories.push(Category {
+                id: number(category, "id")?,
+                name: category.attr("name").unwrap_or_default().to_string(),
+                subcategories,
+            });
+        }
+    }
+
+    let mut genres = None;
+    if let Some(element) = root.child("genres") {
+        let mut list = Vec::new();
+        for genre in element.children("genre") {
+            list.push(Genre {
+                id: number(genre, "id")?,
+                category_id: number(genre, "categoryid")?,
+                name: genre.attr("name").unwrap_or_default().to_string(),
+            });
+        }
+        genres = Some(list);
+    }
+
+    let mut tags = None;
+    if let Some(element) = root.child("tags") {
+        let mut list = Vec::new();
+        for tag in element.children("tag") {
+            list.push(Tag {
+                name: tag.attr("name").unwrap_or_default().to_string(),
+                description: tag.attr("description").unwrap_or_default().to_string(),
+            });
+        }
+        tags = Some(list);
+    }
+
+    return Ok(Caps {
+        server_info,
+        limits,
+        searching,
+        categories,
+        genres,
+        tags,
+    });
+}
+
+/// Parses an `<item>` of a search feed into a [`Torrent`]
+fn parse_item(item: &Element) -> Result<Torrent, FeedError> {
+    let mut torrent = Torrent {
+        title: item
+            .child_text("title")
+            .ok_or_else(|| FeedError::Invalid("item without a `<title>`".to_string()))?,
+        description: item.child_text("description"),
+        size: 0,
+        category_ids: Vec::new(),
+        torrent_file_url: None,
+        magnet_uri: None,
+        link: item.child_text("link"),
+        pub_date: item.child_text("pubDate"),
+        attributes: Default::default(),
+        other_attributes: None,
+    };
+
+    let mut size = None;
+    if let Some(enclosure) = item.child("enclosure") {
+        if let Some(url) = enclosure.attr("url").filter(|url| !url.is_empty()) {
+            if url.starts_with("magnet:") {
+                torrent.magnet_uri = Some(url.to_string());
+            } else {
+                torrent.torrent_file_url = Some(url.to_string());
+            }
+        }
+        // the length is often just `0`, so it's only used if there's nothing better
+        size = optional_number(enclosure, "length")?.filter(|length| *length > 0);
+    }
+    if let Some(text) = item.child_text("size") {
+        size = Some(parse_number(&text, "size")?);
+    }
+
+    let mut other_attributes = HashMap::new();
+    for attr in item.children("attr") {
+        let namespace = attr.namespace.as_deref();
+        if namespace != Some(TORZNAB_NAMESPACE) && namespace != Some(NEWZNAB_NAMESPACE) {
+            continue;
+        }
+        let name = attr.attr("name").unwrap_or_default();
+        let value = attr.attr("value").unwrap_or_default();
+        match name {
+            "size" => {
+                size = Some(parse_number(value, "size")?);
+            }
+            "category" => {
+                torrent.category_ids.push(parse_number(value, "category")?);
+            }
+            "magneturl" => {
+                torrent.magnet_uri = Some(value.to_string());
+            }
+            _ => {
+                if !torrent.attributes.set(name, value) {
+                    other_attributes.insert(name.to_string(), value.to_string());
+                }
+            }
+        }
+    }
+    torrent.size = size.unwrap_or(0);
+    if !other_attributes.is_empty() {
+        torrent.other_attributes = Some(other_attributes);
+    }
+
+    // the link falls back to the .torrent URL, then the magnet URI, so it's only kept if it's something else
+    if torrent.link.is_some()
+        && (torrent.link == torrent.torrent_file_url || torrent.link == torrent.magnet_uri)
+    {
+        torrent.link = None;
+    }
+    return Ok(torrent);
+}
+
+/// An XML element, with only the parts that are needed for parsing responses
+struct Element {
+    /// The element's name, without its namespace prefix (e.g. `attr` for `torznab:attr`)
+    name: String,
+    /// The element's namespace URI, if any
+    namespace: Option<String>,
+    /// The element's attributes, by name (without their prefixes)
+    attributes: HashMap<String, String>,
+    /// The child elements, in order
+    children: Vec<Element>,
+    /// The text directly in the element, including CDATA
+    text: String,
+}
+
+impl Element {
+    /// Returns the value of the attribute `name`
+    fn attr(&self, name: &str) -> Option<&str> {
+        return self.attributes.get(name).map(|value| value.as_str());
+    }
+
+    /// Returns the first child element called `name`
+    fn child(&self, name: &str) -> Option<&Element> {
+        return self.children.iter().find(|child| child.name == name);
+    }
+
+    /// Returns all the child elements called `name`
+    fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> + 'a {
+        return self.children.iter().filter(move |child| child.name == name);
+    }
+
+    /// Returns the trimmed text of the first child element called `name`, or `None` if it's missing or empty
+    fn child_text(&self, name: &str) -> Option<String> {
+        return self
+            .child(name)
+            .map(|child| child.text.trim().to_string())
+            .filter(|text| !text.is_empty());
+    }
+}
+
+/// Parses an XML document into its root element, returning [`FeedError::Torznab`] if it's a Torznab error
+fn parse_document(xml: &str) -> Result<Element, FeedError> {
+    let mut stack: Vec<Element> = Vec::new();
+    for event in EventReader::new(xml.as_bytes()) {
+        match event.map_err(|e| FeedError::Xml(e.to_string()))? {
+            XmlEvent::StartElement {
+                name, attributes, ..
+            } => {
+                stack.push(Element {
+                    name: name.local_name,
+                    namespace: name.namespace,
+                    attributes: attributes
+                        .into_iter()
+                        .map(|attribute| (attribute.name.local_name, attribute.value))
+                        .collect(),
+                    children: Vec::new(),
+                    text: String::new(),
+                });
+            }
+            XmlEvent::EndElement { .. } => {
+                // the parser checks that elements are balanced, so there's always one to pop
+                let element = stack.pop().unwrap();
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(element),
+                    None => {
+                        return check_error(element);
+                    }
+                }
+            }
+            XmlEvent::Characters(text) | XmlEvent::CData(text) => {
+                if let Some(element) = stack.last_mut() {
+                    element.text.push_str(&text);
+                }
+            }
+            _ => {}
+        }
+    }
+    return Err(FeedError::Xml("no root element".to_string()));
+}
+
+/// Returns [`FeedError::Torznab`] if `root` is an `<error>`, otherwise returns `root` as-is
+fn check_error(root: Element) -> Result<Element, FeedError> {
+    if root.name != "error" {
+        return Ok(root);
+    }
+    let code = number(&root, "code")?;
+    return Err(FeedError::Torznab(TorznabError::from_code(
+        code,
+        root.attr("description").unwrap_or_default(),
+    )));
+}
+
+/// Parses the numeric attribute `name` of `element`, which must be present
+fn number<T: FromStr>(element: &Element, name: &str) -> Result<T, FeedError> {
+    match optional_number(element, name)? {
+        Some(value) => {
+            return Ok(value);
+        }
+        None => {
+            return Err(FeedError::Invalid(format!(
+                "`<{}>` is missing `{}`",
+                element.name, name
+            )));
+        }
+    }
+}
+
+/// Parses the numeric attribute `name` of `element`, if it's present
+fn optional_number<T: FromStr>(element: &Element, name: &str) -> Result<Option<T>, FeedError> {
+    match element.attr(name) {
+        Some(value) => {
+            return Ok(Some(parse_number(value, name)?));
+        }
+        None => {
+            return Ok(None);
+        }
+    }
+}
+
+/// Parses `value` as a number, with `name` in the error if it isn't one
+fn parse_number<T: FromStr>(value: &str, name: &str) -> Result<T, FeedError> {
+    return value
+        .trim()
+        .parse()
+        .map_err(|_| FeedError::Invalid(format!("invalid `{}`: `{}`", name, value)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{handle, ApiRequest};
+    use crate::catalog::Catalog;
+    use crate::data::{Config, TorrentAttributes};
+
+    #[test]
+    fn parse_search_feed() {
+        let feed = parse_feed(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+            <rss version="1.0" xmlns:torznab="http://torznab.com/schemas/2015/feed" xmlns:newznab="http://www.newznab.com/DTD/2010/feeds/attributes/">
+                <channel>
+                    <title>Some indexer</title>
+                    <newznab:response offset="10" total="123" />
+                    <item>
+                        <title>Some Show S01E01</title>
+                        <description><![CDATA[a <b>good</b> one]]></description>
+                        <link>https://example.com/details/1</link>
+                        <pubDate>Tue, 10 Jun 2003 04:00:00 GMT</pubDate>
+                        <enclosure url="https://example.com/1.torrent" length="0" type="application/x-bittorrent" />
+                        <torznab:attr name="size" value="2048" />
+                        <torznab:attr name="category" value="5000" />
+                        <torznab:attr name="category" value="5040" />
+                        <torznab:attr name="magneturl" value="magnet:?xt=urn:btih:abc" />
+                        <torznab:attr name="seeders" value="10" />
+                        <torznab:attr name="imdb" value="944947" />
+                        <torznab:attr name="custom" value="yes" />
+                    </item>
+                    <item>
+                        <title>Other</title>
+                        <description />
+                        <link>magnet:?xt=urn:btih:def</link>
+                        <enclosure url="magnet:?xt=urn:btih:def" length="100" type="application/x-bittorrent;x-scheme-handler/magnet" />
+                    </item>
+                </channel>
+            </rss>"#,
+        )
+        .unwrap();
+
+        assert_eq!(feed.title.as_deref(), Some("Some indexer"));
+        assert_eq!((feed.offset, feed.total), (Some(10), Some(123)));
+
+        let first = &feed.torrents[0];
+        assert_eq!(first.description.as_deref(), Some("a <b>good</b> one"));
+        assert_eq!(first.size, 2048);
+        assert_eq!(first.category_ids, vec![5000, 5040]);
+        assert_eq!(
+            first.torrent_file_url.as_deref(),
+            Some("https://example.com/1.torrent")
+        );
+        assert_eq!(first.magnet_uri.as_deref(), Some("magnet:?xt=urn:btih:abc"));
+        assert_eq!(first.link.as_deref(), Some("https://example.com/details/1"));
+        assert_eq!(first.attributes.seeders, Some(10));
+        assert_eq!(first.attributes.imdb_id.as_deref(), Some("tt0944947"));
+        assert_eq!(
+            first.other_attributes,
+            Some(HashMap::from([("custom".to_string(), "yes".to_string())]))
+        );
+
+        let second = &feed.torrents[1];
+        assert_eq!(second.description, None);
+        assert_eq!(second.size, 100);
+        assert_eq!(
+            second.magnet_uri.as_deref(),
+            Some("magnet:?xt=urn:btih:def")
+        );
+        assert_eq!(second.link, None);
+    }
+
+    #[test]
+    fn parse_newznab_feed() {
+        let torrents = parse_torrents(
+            r#"<rss version="2.0" xmlns:nz="http://www.newznab.com/DTD/2010/feeds/attributes/">
+                <channel>
+                    <item>
+                        <title>Some Album</title>
+                        <size>4096</size>
+                        <category>Audio &gt; MP3</category>
+                        <enclosure url="https://example.com/1.nzb" length="0" type="application/x-nzb" />
+                        <nz:attr name="category" value="3010" />
+                        <nz:attr name="grabs" value="7" />
+                        <nz:attr name="artist" value="Someone" />
+                    </item>
+                </channel>
+            </rss>"#,
+        )
+        .unwrap();
+
+        assert_eq!(torrents[0].size, 4096);
+        assert_eq!(torrents[0].category_ids, vec![3010]);
+        assert_eq!(
+            torrents[0].torrent_file_url.as_deref(),
+            Some("https://example.com/1.nzb")
+        );
+        assert_eq!(torrents[0].attributes.grabs, Some(7));
+        assert_eq!(torrents[0].attributes.artist.as_deref(), Some("Someone"));
+    }
+
+    fn round_trip_config(torrents: Vec<Torrent>) -> Config {
+        let caps = Caps::builder()
+            .server_info("title", "Round trip")
+            .server_info("version", "1.0")
+            .limits(100, 50)
+            .search_info(
+                SearchInfo::builder("search")
+                    .supported_param("q")
+                    .build()
+                    .unwrap(),
+            )
+            .search_info(
+                SearchInfo::builder("tv-search")
+                    .available(false)
+                    .supported_params(["q", "season", "ep"])
+                    .build()
+                    .unwrap(),
+            )
+            .category(
+                Category::builder(5000, "TV")
+                    .subcategory(5030, "TV/SD")
+                    .subcategory(5040, "TV/HD")
+                    .build()
+                    .unwrap(),
+            )
+            .genre(1, 5000, "Kids")
+            .tag("trusted", "Uploader has high reputation")
+            .tag("internal", "Uploader is an internal release group")
+            .build()
+            .unwrap();
+        return Config::builder(caps)
+            .search(Catalog::new(torrents))
+            .build()
+            .unwrap();
+    }
+
+    #[actix_rt::test]
+    async fn caps_round_trip() {
+        let conf = round_trip_config(vec![]);
+        let response = handle(&conf, ApiRequest::from_query_string("t=caps")).await;
+        assert_eq!(parse_caps(&response.body).unwrap(), conf.caps);
+    }
+
+    #[actix_rt::test]
+    async fn feed_round_trip() {
+        let torrents = vec![
+            Torrent::builder("Some Show S01E01 1080p", 2484345508)
+                .description("the first one")
+                .category(5000)
+                .category(5040)
+                .torrent_file_url("http://localhost/1.torrent")
+                .magnet_uri("magnet:?xt=urn:btih:abc")
+                .link("http://localhost/details/1")
+                .pub_date("Tue, 10 Jun 2003 04:00:00 GMT")
+                .attributes(TorrentAttributes {
+                    seeders: Some(10),
+                    leechers: Some(2),
+                    infohash: Some("abc".to_string()),
+                    download_volume_factor: Some(0.5),
+                    minimum_seed_time: Some(172800),
+                    imdb_id: Some("tt0944947".to_string()),
+                    season: Some(1),
+                    episode: Some(1),
+                    genre: Some("Drama, Fantasy".to_string()),
+                    ..Default::default()
+                })
+                .attribute("resolution", "1080p")
+                .build()
+                .unwrap(),
+            Torrent::builder("Some Show S01E02 & <friends>", 1)
+                .category(5030)
+                .magnet_uri("magnet:?xt=urn:btih:def&dn=Some+Show")
+                .build()
+                .unwrap(),
+        ];
+        let conf = round_trip_config(torrents.clone());
+
+        let response = handle(&conf, ApiRequest::from_query_string("t=search&extended=1")).await;
+        let feed = parse_feed(&response.body).unwrap();
+        assert_eq!(feed.title.as_deref(), Some("Round trip"));
+        assert_eq!((feed.offset, feed.total), (Some(0), Some(2)));
+        assert_eq!(feed.torrents, torrents);
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(
+            parse_feed(r#"<error code="100" description="Incorrect user credentials"/>"#),
+            Err(FeedError::Torznab(TorznabError::IncorrectCredentials))
+        );
+        assert_eq!(
+            parse_caps(r#"<error code="201" description="Incorrect parameter: limit"/>"#),
+            Err(FeedError::Torznab(TorznabError::IncorrectParameter(
+                "limit".to_string()
+            )))
+        );
+        assert!(matches!(
+            parse_feed("<rss><channel>"),
+            Err(FeedError::Xml(_))
+        ));
+        assert!(matches!(parse_feed("<caps/>"), Err(FeedError::Invalid(_))));
+    }
+}
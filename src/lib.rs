@@ -2,40 +2,261 @@
 //! - **[Tutorial](notes::tutorial)**
 //! - [Minor implementation and usage notes](notes::notes)
 #![warn(missing_docs)]
+#![allow(clippy::needless_return)]
 #![doc = include_str!("../README.md")]
-pub(crate) mod api;
+pub mod aggregator;
+pub mod api;
+#[cfg(feature = "axum")]
+pub mod axum_api;
+pub mod builder;
+pub mod catalog;
+pub mod categories;
+pub mod client;
 pub mod data;
+#[cfg(test)]
 mod dummy;
+pub mod feed;
+pub mod loading;
+#[cfg(feature = "rocket")]
+mod rocket_api;
+pub mod server;
+pub mod validation;
+
+use std::fmt;
+use validation::ConfigProblem;
 
-use rocket;
 // imports for docs
+#[allow(unused_imports)]
 use crate::data::Config;
+#[allow(unused_imports)]
+use crate::server::{ServerHandle, ServerOptions};
+
+#[derive(Debug)]
+/// An error from running the server, with [`run`] or one of the other adapters
+pub enum RunError {
+    /// The config has problems (see [`Config::validate`]), so the server wasn't started
+    InvalidConfig(Vec<ConfigProblem>),
+    /// Rocket failed to launch, or errored while running
+    #[cfg(feature = "rocket")]
+    Rocket(Box<rocket::Error>),
+    /// The server couldn't bind to its address, or errored while running
+    Io(std::io::Error),
+    /// One of the [`ServerOptions`] isn't supported by the adapter (e.g. a Unix socket with Rocket), so the server wasn't started
+    UnsupportedOption(String),
+}
+
+impl fmt::Display for RunError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RunError::InvalidConfig(problems) => {
+                let problems: Vec<String> = problems.iter().map(|p| p.to_string()).collect();
+                return write!(f, "invalid config: {}", problems.join("; "));
+            }
+            #[cfg(feature = "rocket")]
+            RunError::Rocket(e) => {
+                return write!(f, "{}", e);
+            }
+            RunError::Io(e) => {
+                return write!(f, "{}", e);
+            }
+            RunError::UnsupportedOption(reason) => {
+                return write!(f, "unsupported server option: {}", reason);
+            }
+        }
+    }
+}
+
+impl std::error::Error for RunError {}
+
+impl From<std::io::Error> for RunError {
+    fn from(e: std::io::Error) -> RunError {
+        return RunError::Io(e);
+    }
+}
+
+#[cfg(feature = "rocket")]
+impl From<rocket::Error> for RunError {
+    fn from(e: rocket::Error) -> RunError {
+        return RunError::Rocket(Box::new(e));
+    }
+}
+
+/// Returns all the routes of the Torznab API, for mounting in your own Rocket instance
+///
+/// The routes are relative to the API's path, so they should be mounted at e.g. `/api`; the [`Config`] also needs to be managed by Rocket.
+/// Usually [`attach`] is easier, since it does all that (and validates the config) for you.
+///
+/// These are a thin adapter over [`api::handle`], which can be used to serve the API with other frameworks.
+#[cfg(feature = "rocket")]
+pub fn routes() -> Vec<rocket::Route> {
+    return rocket::routes![rocket_api::endpoint];
+}
+
+/// Adds the Torznab API to an existing Rocket instance, at `base_path` (e.g. `/api`, or `/torznab/api`)
+///
+/// This mounts the [`routes`] and manages the [`Config`], so you can add your own routes, fairings, and state alongside the API; note that only one Torznab API can be attached to each Rocket instance.
+/// It also registers a 404 catcher for `base_path`, so that anything under it that isn't the API gets a Torznab error (`202`) rather than Rocket's HTML page.
+/// The config is checked with [`Config::validate`] first.
+///
+/// Example:
+/// ```
+/// # fn get_config() -> torznab_toolkit::data::Config { unimplemented!() }
+/// # fn example() -> Result<(), torznab_toolkit::RunError> {
+/// #[rocket::get("/")]
+/// fn index() -> &'static str {
+///     "Hello, world!"
+/// }
+///
+/// let config = get_config();
+/// let rocket = rocket::build().mount("/", rocket::routes![index]);
+/// let rocket = torznab_toolkit::attach(rocket, "/torznab/api", config)?;
+/// // ...then launch it as usual
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "rocket")]
+pub fn attach(
+    rocket: rocket::Rocket<rocket::Build>,
+    base_path: impl AsRef<str>,
+    conf: data::Config,
+) -> Result<rocket::Rocket<rocket::Build>, RunError> {
+    if let Err(problems) = conf.validate() {
+        return Err(RunError::InvalidConfig(problems));
+    }
+
+    let base_path = normalize_base_path(base_path);
+    return Ok(rocket
+        .mount(base_path.clone(), routes())
+        .register(base_path, rocket::catchers![rocket_api::not_found])
+        .manage(conf));
+}
+
+/// Normalizes the path the API is served at, so that it has a leading slash but no trailing one (e.g. `torznab/api/` becomes `/torznab/api`)
+#[cfg(any(feature = "rocket", feature = "axum"))]
+pub(crate) fn normalize_base_path(base_path: impl AsRef<str>) -> String {
+    let mut base_path = base_path.as_ref().trim_end_matches('/').to_string();
+    if !base_path.starts_with('/') {
+        base_path.insert(0, '/');
+    }
+    return base_path;
+}
+
+/// Builds a Rocket instance serving just the Torznab API at `base_path` (e.g. `/api`), without launching it
+///
+/// This is the same as [`attach`] with `rocket::build()`; Rocket's own config (address, port, etc.) is still read as usual.
+#[cfg(feature = "rocket")]
+pub fn build(
+    conf: data::Config,
+    base_path: impl AsRef<str>,
+) -> Result<rocket::Rocket<rocket::Build>, RunError> {
+    return build_with_options(conf, base_path, &ServerOptions::default());
+}
+
+/// Builds a Rocket instance like [`build`], but with the [`ServerOptions`] that are set overriding Rocket's own config
+///
+/// Returns [`RunError::UnsupportedOption`] for a Unix socket, or for TLS without the `tls` feature.
+#[cfg(feature = "rocket")]
+pub fn build_with_options(
+    conf: data::Config,
+    base_path: impl AsRef<str>,
+    options: &ServerOptions,
+) -> Result<rocket::Rocket<rocket::Build>, RunError> {
+    let figment = rocket_api::figment(options)?;
+    return attach(rocket::custom(figment), base_path, conf);
+}
+
+/// Runs the server, serving the API at `/api`
+///
+/// The config is checked with [`Config::validate`] first, and the server isn't started if there are any problems.
+///
+/// Returns `Ok(true)` if it succeeds, otherwise returns the config's problems, or the error from Rocket
+///
+/// To use axum instead, see `axum_api::run` (requires the `axum` feature).
+#[cfg(feature = "rocket")]
+pub async fn run(conf: data::Config) -> Result<bool, RunError> {
+    return run_with_options(conf, ServerOptions::default()).await;
+}
 
-/// Runs the server
-///
-/// Returns `Ok(true)` if it succeeds, otherwise returns the error from Rocket
-pub async fn run(conf: data::Config) -> Result<bool, rocket::Error> {
-    match rocket::build()
-        .mount(
-            "/",
-            rocket::routes![
-                api::caps,
-                api::search,
-                api::tv_search,
-                api::movie_search,
-                api::music_search,
-                api::book_search
-            ],
-        )
-        .manage(conf)
-        .launch()
-        .await
-    {
+/// Runs the server like [`run`], but with the [`ServerOptions`] that are set (e.g. the address and port) overriding Rocket's own config
+///
+/// Returns [`RunError::UnsupportedOption`] for a Unix socket, or for TLS without the `tls` feature.
+#[cfg(feature = "rocket")]
+pub async fn run_with_options(
+    conf: data::Config,
+    options: ServerOptions,
+) -> Result<bool, RunError> {
+    match build_with_options(conf, "/api", &options)?.launch().await {
         Ok(_) => {
             return Ok(true);
         }
         Err(e) => {
-            return Err(e);
+            return Err(RunError::Rocket(Box::new(e)));
+        }
+    }
+}
+
+/// Starts the server in the background, serving the API at `/api`, and returns a [`ServerHandle`] to it once it's listening
+///
+/// The handle has the address it's actually listening on (e.g. the port that was picked, with port `0`), and can gracefully shut it down.
+/// Like [`run_with_options`], the config is checked first, and the [`ServerOptions`] that are set override Rocket's own config.
+///
+/// This must be called from within a Tokio runtime (which Rocket needs anyway).
+///
+/// Example:
+/// ```no_run
+/// # async fn example(config: torznab_toolkit::data::Config) -> Result<(), torznab_toolkit::RunError> {
+/// use torznab_toolkit::server::ServerOptions;
+///
+/// let options = ServerOptions {
+///     port: Some(0), // any free port
+///     ..Default::default()
+/// };
+/// let server = torznab_toolkit::spawn(config, options).await?;
+/// println!("listening on {}", server.local_addr().unwrap());
+///
+/// // ...later, e.g. on SIGTERM
+/// server.shutdown().await?;
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "rocket")]
+pub async fn spawn(conf: data::Config, options: ServerOptions) -> Result<ServerHandle, RunError> {
+    let (address_sender, address_receiver) = tokio::sync::oneshot::channel();
+    let rocket = build_with_options(conf, "/api", &options)?
+        .attach(rocket::fairing::AdHoc::on_liftoff(
+            "Bound address",
+            move |rocket| {
+                Box::pin(async move {
+                    let config = rocket.config();
+                    let _ =
+                        address_sender.send(std::net::SocketAddr::new(config.address, config.port));
+                })
+            },
+        ))
+        .ignite()
+        .await?;
+
+    let shutdown = rocket.shutdown();
+    let task = tokio::spawn(async move {
+        rocket.launch().await?;
+        return Ok(());
+    });
+
+    match address_receiver.await {
+        Ok(address) => {
+            return Ok(ServerHandle::new(
+                Some(address),
+                move || shutdown.notify(),
+                task,
+            ));
+        }
+        Err(_) => {
+            // it stopped before lifting off, so it failed to launch (e.g. the address is in use)
+            let handle = ServerHandle::new(None, || {}, task);
+            handle.wait().await?;
+            return Err(RunError::Io(std::io::Error::other(
+                "the server stopped before it started listening",
+            )));
         }
     }
 }
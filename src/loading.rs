@@ -0,0 +1,213 @@
+//! Loading [`Caps`] from a TOML, JSON, or YAML file, so that e.g. the categories can be changed without recompiling
+//!
+//! Each format needs its feature enabled: `json` (enabled by default), `toml`, or `yaml`.
+//! The files have the same structure and field names as [`Caps`]; `searching`, `subcategories`, `supported_params`, `server_info`, `genres`, and `tags` can be left out, and `available` defaults to `true`.
+//!
+//! Example, in TOML:
+//! ```toml
+//! [server_info]
+//! title = "My indexer"
+//!
+//! [limits]
+//! max = 100
+//! default = 50
+//!
+//! [[searching]]
+//! search_type = "tv-search"
+//! supported_params = ["q", "season", "ep"]
+//!
+//! [[categories]]
+//! id = 5000
+//! name = "TV"
+//! subcategories = [{ id = 5040, name = "TV/HD" }]
+//! ```
+//!
+//! The loaded caps are checked with [`Caps::validate`], so problems are caught when loading rather than by clients.
+use crate::data::Caps;
+use crate::validation::ConfigProblem;
+use std::fmt;
+use std::path::Path;
+
+#[derive(Debug)]
+/// An error from loading [`Caps`] from a file or string
+pub enum LoadError {
+    /// The file couldn't be read
+    Io(std::io::Error),
+    /// The file couldn't be parsed; contains the parser's error
+    Parse(String),
+    /// The file's extension isn't one of the formats that are enabled (`json`, `toml`, `yaml`/`yml`); contains the extension
+    UnknownFormat(String),
+    /// The caps were loaded, but have problems (see [`Caps::validate`])
+    Invalid(Vec<ConfigProblem>),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Io(e) => {
+                return write!(f, "{}", e);
+            }
+            LoadError::Parse(e) => {
+                return write!(f, "couldn't parse caps: {}", e);
+            }
+            LoadError::UnknownFormat(extension) => {
+                return write!(
+                    f,
+                    "unknown caps file format `{}`; the enabled formats are: {}",
+                    extension,
+                    FORMATS.join(", ")
+                );
+            }
+            LoadError::Invalid(problems) => {
+                let problems: Vec<String> = problems.iter().map(|p| p.to_string()).collect();
+                return write!(f, "invalid caps: {}", problems.join("; "));
+            }
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+impl From<std::io::Error> for LoadError {
+    fn from(e: std::io::Error) -> LoadError {
+        return LoadError::Io(e);
+    }
+}
+
+/// The file extensions of the formats that are enabled
+const FORMATS: &[&str] = &[
+    #[cfg(feature = "json")]
+    "json",
+    #[cfg(feature = "toml")]
+    "toml",
+    #[cfg(feature = "yaml")]
+    "yaml",
+    #[cfg(feature = "yaml")]
+    "yml",
+];
+
+/// Turns the result of parsing into a [`LoadError`] if it failed, then validates the caps
+#[cfg(any(feature = "json", feature = "toml", feature = "yaml"))]
+fn checked<E: fmt::Display>(parsed: Result<Caps, E>) -> Result<Caps, LoadError> {
+    let caps = parsed.map_err(|e| LoadError::Parse(e.to_string()))?;
+    caps.validate().map_err(LoadError::Invalid)?;
+    return Ok(caps);
+}
+
+impl Caps {
+    /// Loads the caps from a file, with the format picked by its extension (`.json`, `.toml`, or `.yaml`/`.yml`)
+    ///
+    /// Returns [`LoadError::UnknownFormat`] if the format's feature isn't enabled.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Caps, LoadError> {
+        let path = path.as_ref();
+        let extension = path
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        if !FORMATS.contains(&extension.as_str()) {
+            return Err(LoadError::UnknownFormat(extension));
+        }
+
+        #[allow(unused_variables)] // unused if no formats are enabled
+        let contents = std::fs::read_to_string(path)?;
+        match extension.as_str() {
+            #[cfg(feature = "json")]
+            "json" => {
+                return Caps::from_json(&contents);
+            }
+            #[cfg(feature = "toml")]
+            "toml" => {
+                return Caps::from_toml(&contents);
+            }
+            #[cfg(feature = "yaml")]
+            "yaml" | "yml" => {
+                return Caps::from_yaml(&contents);
+            }
+            _ => {
+                return Err(LoadError::UnknownFormat(extension));
+            }
+        }
+    }
+
+    /// Loads the caps from a JSON string; requires the `json` feature
+    #[cfg(feature = "json")]
+    pub fn from_json(json: &str) -> Result<Caps, LoadError> {
+        return checked(serde_json::from_str(json));
+    }
+
+    /// Loads the caps from a TOML string; requires the `toml` feature
+    #[cfg(feature = "toml")]
+    pub fn from_toml(toml: &str) -> Result<Caps, LoadError> {
+        return checked(toml::from_str(toml));
+    }
+
+    /// Loads the caps from a YAML string; requires the `yaml` feature
+    #[cfg(feature = "yaml")]
+    pub fn from_yaml(yaml: &str) -> Result<Caps, LoadError> {
+        return checked(serde_yaml::from_str(yaml));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(any(feature = "json", feature = "yaml"))]
+    use crate::dummy::create_empty_config;
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json_round_trip() {
+        let caps = create_empty_config().caps;
+        let json = serde_json::to_string(&caps).unwrap();
+        assert_eq!(Caps::from_json(&json).unwrap(), caps);
+
+        let json =
+            r#"{"limits": {"max": 10, "default": 20}, "categories": [{"id": 5000, "name": "TV"}]}"#;
+        assert!(matches!(
+            Caps::from_json(json),
+            Err(LoadError::Invalid(problems)) if problems == vec![ConfigProblem::InvalidLimits { max: 10, default: 20 }]
+        ));
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn toml_with_defaults() {
+        let caps = Caps::from_toml(
+            r#"
+            [limits]
+            max = 100
+            default = 50
+
+            [[searching]]
+            search_type = "tv-search"
+            supported_params = ["q", "season", "ep"]
+
+            [[categories]]
+            id = 5000
+            name = "TV"
+            subcategories = [{ id = 5040, name = "TV/HD" }]
+            "#,
+        )
+        .unwrap();
+
+        assert!(caps.searching[0].available);
+        assert_eq!(caps.categories[0].subcategories[0].id, 5040);
+        assert_eq!(caps.server_info, None);
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn yaml_round_trip() {
+        let caps = create_empty_config().caps;
+        let yaml = serde_yaml::to_string(&caps).unwrap();
+        assert_eq!(Caps::from_yaml(&yaml).unwrap(), caps);
+    }
+
+    #[test]
+    fn unknown_format() {
+        assert!(matches!(
+            Caps::from_file("caps.ini"),
+            Err(LoadError::UnknownFormat(extension)) if extension == "ini"
+        ));
+    }
+}
@@ -0,0 +1,243 @@
+//! `torznab-toolkit`: serves the Torznab API over a static catalog of torrents, for demos and test environments
+//!
+//! Usage:
+//! ```text
+//! torznab-toolkit --caps <FILE> --catalog <FILE> [--address <IP>] [--port <PORT>] [--apikey <KEY>]
+//! ```
+//!
+//! The caps file is loaded with [`Caps::from_file`], and the catalog with [`Catalog::from_file`]; see their docs for the formats.
+//! If the caps don't list any search types, a plain `search` with `q` is added.
+#![allow(clippy::needless_return)]
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::process::exit;
+use torznab_toolkit::catalog::Catalog;
+use torznab_toolkit::data::{Caps, Config, SearchInfo};
+use torznab_toolkit::server::ServerOptions;
+
+const USAGE: &str = "usage: torznab-toolkit --caps <FILE> --catalog <FILE> [--address <IP>] [--port <PORT>] [--apikey <KEY>]";
+
+/// The command-line arguments
+struct Args {
+    caps: PathBuf,
+    catalog: PathBuf,
+    address: Option<IpAddr>,
+    port: Option<u16>,
+    apikey: Option<String>,
+}
+
+impl Args {
+    /// Parses the arguments, returning `None` if the usage was asked for (`-h`/`--help`), or a message to show if they're invalid
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Option<Args>, String> {
+        let mut caps = None;
+        let mut catalog = None;
+        let mut address = None;
+        let mut port = None;
+        let mut apikey = None;
+
+        while let Some(arg) = args.next() {
+            if arg == "-h" || arg == "--help" {
+                return Ok(None);
+            }
+            // a flag where the value should be (e.g. `--caps --help`) means the value was left out
+            let value = match args.next() {
+                Some(value) if !value.starts_with("--") => value,
+                _ => return Err(format!("missing value for `{}`\n{}", arg, USAGE)),
+            };
+            match arg.as_str() {
+                "--caps" => caps = Some(PathBuf::from(value)),
+                "--catalog" => catalog = Some(PathBuf::from(value)),
+                "--address" => {
+                    address = Some(
+                        value
+                            .parse()
+                            .map_err(|_| format!("invalid address `{}`", value))?,
+                    )
+                }
+                "--port" => {
+                    port = Some(
+                        value
+                            .parse()
+                            .map_err(|_| format!("invalid port `{}`", value))?,
+                    )
+                }
+                "--apikey" => apikey = Some(value),
+                _ => return Err(format!("unknown argument `{}`\n{}", arg, USAGE)),
+            }
+        }
+
+        return Ok(Some(Args {
+            caps: caps.ok_or_else(|| format!("missing `--caps`\n{}", USAGE))?,
+            catalog: catalog.ok_or_else(|| format!("missing `--catalog`\n{}", USAGE))?,
+            address,
+            port,
+            apikey,
+        }));
+    }
+}
+
+/// Loads the caps and catalog, and builds the config to serve them with
+fn load(args: &Args) -> Result<Config, String> {
+    let mut caps =
+        Caps::from_file(&args.caps).map_err(|e| format!("{}: {}", args.caps.display(), e))?;
+    if caps.searching.is_empty() {
+        caps.searching.push(
+            SearchInfo::builder("search")
+                .supported_param("q")
+                .build()
+                .map_err(|e| e.to_string())?,
+        );
+    }
+    let catalog = Catalog::from_file(&args.catalog)
+        .map_err(|e| format!("{}: {}", args.catalog.display(), e))?;
+
+    let mut builder = Config::builder(caps)
+        .search(catalog)
+        .filter_categories(true);
+    if let Some(apikey) = args.apikey.clone() {
+        builder = builder.auth(move |key: String| -> Result<bool, String> {
+            return Ok(key == apikey);
+        });
+    }
+    return builder.build().map_err(|e| e.to_string());
+}
+
+#[rocket::main]
+async fn main() {
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return;
+        }
+        Err(message) => {
+            eprintln!("{}", message);
+            exit(2);
+        }
+    };
+    let conf = match load(&args) {
+        Ok(conf) => conf,
+        Err(message) => {
+            eprintln!("{}", message);
+            exit(1);
+        }
+    };
+
+    let options = ServerOptions {
+        address: args.address,
+        port: args.port,
+        ..Default::default()
+    };
+    if let Err(e) = torznab_toolkit::run_with_options(conf, options).await {
+        eprintln!("{}", e);
+        exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Option<Args>, String> {
+        return Args::parse(args.iter().map(|arg| arg.to_string()));
+    }
+
+    /// The first line of the error message for `args`, i.e. without the usage
+    fn parse_error(args: &[&str]) -> String {
+        let message = parse(args).err().expect("should have failed");
+        return message.lines().next().unwrap().to_string();
+    }
+
+    #[test]
+    fn parses_arguments() {
+        let args = parse(&[
+            "--caps",
+            "caps.json",
+            "--catalog",
+            "torrents.csv",
+            "--address",
+            "0.0.0.0",
+            "--port",
+            "9117",
+            "--apikey",
+            "letmein",
+        ])
+        .unwrap()
+        .unwrap();
+        assert_eq!(args.caps, PathBuf::from("caps.json"));
+        assert_eq!(args.catalog, PathBuf::from("torrents.csv"));
+        assert_eq!(args.address, Some("0.0.0.0".parse().unwrap()));
+        assert_eq!(args.port, Some(9117));
+        assert_eq!(args.apikey.as_deref(), Some("letmein"));
+    }
+
+    #[test]
+    fn help_asks_for_the_usage() {
+        assert!(matches!(parse(&["-h"]), Ok(None)));
+        assert!(matches!(
+            parse(&["--caps", "caps.json", "--help"]),
+            Ok(None)
+        ));
+    }
+
+    #[test]
+    fn invalid_arguments_are_rejected() {
+        assert_eq!(parse_error(&["--caps"]), "missing value for `--caps`");
+        assert_eq!(
+            parse_error(&["--caps", "--help"]),
+            "missing value for `--caps`"
+        );
+        assert_eq!(
+            parse_error(&["--verbose", "yes"]),
+            "unknown argument `--verbose`"
+        );
+        assert_eq!(
+            parse_error(&["--catalog", "torrents.csv"]),
+            "missing `--caps`"
+        );
+        assert_eq!(parse_error(&["--caps", "caps.json"]), "missing `--catalog`");
+        assert_eq!(
+            parse_error(&["--caps", "caps.json", "--port", "99999"]),
+            "invalid port `99999`"
+        );
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn load_adds_a_search_when_the_caps_have_none() {
+        let dir = std::env::temp_dir().join(format!("torznab-toolkit-main-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let caps = dir.join("caps.json");
+        std::fs::write(
+            &caps,
+            r#"{"limits": {"max": 100, "default": 50}, "categories": [{"id": 5000, "name": "TV"}]}"#,
+        )
+        .unwrap();
+        let catalog = dir.join("torrents.json");
+        std::fs::write(
+            &catalog,
+            r#"[{"title": "a", "size": 1, "category_ids": [5000], "magnet_uri": "magnet:?xt=urn:btih:a"}]"#,
+        )
+        .unwrap();
+
+        let args = Args {
+            caps,
+            catalog,
+            address: None,
+            port: None,
+            apikey: Some("letmein".to_string()),
+        };
+        let conf = load(&args);
+        std::fs::remove_dir_all(&dir).unwrap();
+        let conf = conf.unwrap();
+        assert_eq!(
+            conf.caps.searching,
+            vec![SearchInfo::builder("search")
+                .supported_param("q")
+                .build()
+                .unwrap()]
+        );
+        assert!(conf.filter_categories);
+        assert!(conf.auth.is_some());
+    }
+}
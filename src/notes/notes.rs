@@ -1,13 +1,21 @@
 //! Notes regarding the usage of torznab-tooolkit, and its implementation of Torznab
 //!
-//! - Please implement whatever attributes for torrents you can whenever possible to improve search results; particularly, `season`, `ep`, and `*id` fields are recommended, besides those listed in [`Torrent`]
-//!   - Potential attributes are listed [here](https://torznab.github.io/spec-1.3-draft/torznab/Specification-v1.3.html#predefined-attributes)
+//! - Please implement whatever attributes for torrents you can whenever possible to improve search results; particularly, `season`, `episode`, and `*id` fields are recommended, besides those listed in [`Torrent`]
+//!   - The predefined attributes are in [`TorrentAttributes`], and are listed [here](https://torznab.github.io/spec-1.3-draft/torznab/Specification-v1.3.html#predefined-attributes); anything else can go in `other_attributes`
+//!   - If there's a magnet URI, it's also listed as the `magneturl` attribute
+//!   - As in the spec, only `size` and `category` are listed by default; other attributes are only listed if they're asked for with `attrs=...`, or if all of them are asked for with `extended=1` (which is what Sonarr, Radarr, etc. do)
 //! - Because the behavior of `length` is ambiguous, torznab-toolkit just sets it to 0; the size is just specified by the `size` attribute
 //!   - See [here](https://torznab.github.io/spec-1.3-draft/revisions/1.0-Torznab-Torrent-Support.html) for details
 //! - Many indexers do not have the appropriate behavior according to the spec when `limit` is negative, and that behavior doesn't even make sense; instead, it follows the behavior of other indexers, and just ignores `limit` if it's negative.
-//! - If a link isn't specified for a [`Torrent`] (`link` in `other_attributes` field), it will fall back to the .torrent URL, then the magnet URI; i.e. you don't have to specify `link` if you don't have a webpage for the torrent.
+//! - If a link isn't specified for a [`Torrent`] (`link` field), it will fall back to the .torrent URL, then the magnet URI; i.e. you don't have to specify `link` if you don't have a webpage for the torrent.
 //!   - Regardless of this, `link` is optional, but some software (e.g. Headphones) breaks if it's not provided.
-//! - Currently if a function returns an [`Err`], torznab-toolkit won't handle it and will just return 500; however, Rocket will log it to the console.
+//! - For TV searches, an `ep` without a `season` is treated as an absolute episode number (as used for anime), and `ep` in the form `MM/DD` as a daily show's episode; see [`Episode`]. Invalid TV parameters are rejected with `201 - Incorrect parameter`.
+//! - If the search backend or authenticator returns an [`Err`], it's sent to the client as a Torznab error response (`<error code="..." description="..."/>`); see [`TorznabError`] for the codes. Plain `String` errors are sent as `900 - Unknown error`.
+//! - If a [`Torrent`] has neither a .torrent URL nor a magnet URI, the whole search fails with a `900` error rather than returning a partial feed.
+//! - Only the search types that are listed in [`Caps::searching`] *and* marked as available can be used; others get a `203 - Function not available` error without reaching the backend. An unknown `t` gets `202 - No such function`.
+//! - By default, search parameters that aren't in the search type's `supportedParams` are still passed on to the backend; set [`Config::unsupported_params`] to strip them, or to reject the request with a `201` error.
+//! - Torrents aren't filtered by the requested categories (`cat`) unless [`Config::filter_categories`] is enabled; if it is, requesting a parent category (e.g. `5000`) also matches its subcategories (e.g. `5040`).
 
 // imports for docs
+#[allow(unused_imports)]
 use crate::data::*;
@@ -1,10 +1,15 @@
 //! ## Structure of the program using this library
 //!
 //! First off, you should create a search function. This function will handle all search types, with what type being specified in the parameter's `search_type` field (`search`, `tv-search`, `movie-search`, `audio-search`, or `movie-search`). Given those parameters, the search function then returns a [`Result`]<[`Vec`]<[`Torrent`]>, [`String`]> object.
-//! The torrents will be listed by the API in the order they're returned here
+//! The torrents will be listed by the API in the order they're returned here, and torznab-toolkit will handle `offset` and `limit` for you
+//!
+//! Errors are sent to the client as Torznab errors; a plain [`String`] error is sent as `900 - Unknown error`, but you can return a specific [`TorznabError`] (e.g. [`TorznabError::RequestLimitReached`]) from a [`SearchBackend`].
+//!
+//! If your search needs its own state (like a database pool or an HTTP client), or needs to `.await` anything, implement [`SearchBackend`] on your own type instead; plain functions like the one below are already [`SearchBackend`]s.
+//! A backend can also do the paging itself (e.g. with `LIMIT`/`OFFSET` in a database query) by returning [`SearchResults::Paged`] along with the total number of results.
 //!
 //! ```
-//! use torznab_toolkit::data::{SearchParameters, Torrent};
+//! use torznab_toolkit::data::{SearchParameters, Torrent, TorrentAttributes};
 //!
 //! fn search(parameters: SearchParameters) -> Result<Vec<Torrent>, String> {
 //!     return Ok(vec![Torrent {
@@ -14,12 +19,19 @@
 //!         category_ids: vec![1010],
 //!         torrent_file_url: Some("http://localhost/totally-normal.torrent".to_string()),
 //!         magnet_uri: Some("magnet:?xt=urn:btih:blahblahblahdothechachacha".to_string()),
+//!         link: None,
+//!         pub_date: None,
+//!         attributes: TorrentAttributes {
+//!             seeders: Some(10),
+//!             ..Default::default()
+//!         },
 //!         other_attributes: None,
 //!     }]);
 //! }
 //! ```
 //!
 //! If you want authentication, you can also create a function for that; returning true indicates that the apikey is valid.
+//! If you need more than that, like a key store, the client's IP or headers, or want to tell the search backend who the request is from, implement [`Authenticator`] instead; the [`Principal`] it returns will be passed to the search backend in [`SearchParameters::principal`].
 //!
 //! ```
 //! fn auth(apikey: String) -> Result<bool, String> {
@@ -31,24 +43,45 @@
 //! ```
 //!
 //! Now you need to configure torznab-toolkit using a [`Config`] object. In total, you'll need the following objects for the config:
-//! - The search function
-//! - The API function (optional)
+//! - The search function or backend, wrapped in an [`Arc`](std::sync::Arc)
+//! - The auth function or authenticator, wrapped in an [`Arc`](std::sync::Arc) (optional)
 //! - The capabilities of the server - i.e.  ([`Caps`])
 //!
 //! Most of the config will be part of [`Caps`]. For details on all these, just check out the doc pages for each of the fields.
 //!
+//! The easiest way to create all of these is with the builders in [`builder`](crate::builder) (e.g. [`Config::builder`], [`Caps::builder`], [`Torrent::builder`]), which check that everything's valid when they're built.
+//! For the categories, it's best to use the standard ones in [`categories`](crate::categories), since that's what clients like Sonarr expect.
+//! The caps can also be loaded from a JSON, TOML, or YAML file with [`Caps::from_file`] (see [`loading`](crate::loading) for the format and the features each needs), so they can be changed without recompiling.
+//!
+//! If you'd rather have a separate search function for each search type, register them with [`ConfigBuilder::search_handler`](crate::builder::ConfigBuilder::search_handler) instead; the `searching` part of the caps (including `supportedParams`) is then filled in from what you registered, so you don't have to keep it in sync yourself.
+//!
+//! Coming from 0.2? `Config` changed in 0.3, so configs written as struct literals need updating: wrap the search and auth functions in an [`Arc`](std::sync::Arc) (`search: Arc::new(search)`, `auth: Some(Arc::new(auth))`), and add `filter_categories: false` and `unsupported_params: UnsupportedParams::Allow`, which behave like 0.2 did. Switching to [`Config::builder`] avoids both.
+//!
 //! With all that, you can now start up the server, which is simple:
 //!
-//! ```
+//! ```ignore
 //! use torznab_toolkit;
 //! let config: torznab_toolkit::data::Config = /* config goes here */
 //!
 //! torznab_toolkit::run(config).await.unwrap();
 //! ```
 //!
+//! Before starting, [`run`] checks the config with [`Config::validate`](crate::data::Config::validate), and returns [`RunError::InvalidConfig`](crate::RunError::InvalidConfig) with a list of all the problems if there are any (e.g. a default limit over the max, or a subcategory that isn't under its parent). The builders run the same checks in `build()`, so this only catches configs put together by hand.
+//!
 //! To easily change what address is listens on and what port, you can use the `ROCKET_ADDRESS` and `ROCKET_PORT` environment variables; the defaults are `127.0.0.1` and `8000`.
 //! For more details on configuring Rocket, see the [Configuration](https://rocket.rs/guide/v0.5/configuration/) page in Rocket's docs - you can also use a `Rocket.toml` file.
+//!
+//! If your program has its own config system, you can instead set the address, port, and TLS certificate/key with [`ServerOptions`](crate::server::ServerOptions), and pass them to [`run_with_options`](crate::run_with_options); anything that's not set still falls back to Rocket's config. A port of `0` picks any free port.
+//!
+//! [`run`] only returns once the server stops (e.g. on Ctrl-C). To keep control of it instead, start it in the background with [`spawn`](crate::spawn), which returns a [`ServerHandle`](crate::server::ServerHandle) with the address it's actually listening on, and a graceful [`shutdown`](crate::server::ServerHandle::shutdown) that lets in-flight searches finish.
+//!
+//! If your program already uses Rocket, or you want to add your own routes, fairings, or state next to the API, use [`attach`](crate::attach) to add the API to your own Rocket instance at whatever path you want (e.g. `/torznab/api`) instead of using [`run`].
+//!
+//! If you'd rather use another web framework (or no framework at all), [`api::handle`](crate::api::handle) is the whole API without Rocket: give it the request's query parameters and headers as an [`ApiRequest`](crate::api::ApiRequest), and it returns the status, content type, and body to respond with.
 
 // imports for the docs
+#[allow(unused_imports)]
 use crate::data::*;
+#[allow(unused_imports)]
+#[cfg(feature = "rocket")]
 use crate::run;
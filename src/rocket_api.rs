@@ -0,0 +1,94 @@
+//! Adapts the framework-agnostic [`api`](crate::api) to Rocket
+//!
+//! There's just one route; everything else (including dispatching on `t`) is done by [`api::handle`].
+use crate::api::{self, ApiRequest, ApiResponse};
+use crate::data::{Config, TorznabError};
+use crate::server::ServerOptions;
+use crate::RunError;
+use rocket::figment::Figment;
+use rocket::http::{ContentType, Status};
+use rocket::request::{self, FromRequest, Request};
+use rocket::response::{self, Responder, Response};
+use rocket::{catch, get, State};
+use std::io::Cursor;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ApiRequest {
+    type Error = std::convert::Infallible;
+
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        let query = match request.uri().query() {
+            Some(query) => query
+                .segments()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            None => Vec::new(),
+        };
+        let headers = request
+            .headers()
+            .iter()
+            .map(|header| (header.name().to_string(), header.value().to_string()))
+            .collect();
+
+        return request::Outcome::Success(ApiRequest {
+            query,
+            headers,
+            client_ip: request.client_ip(),
+        });
+    }
+}
+
+impl<'r> Responder<'r, 'static> for ApiResponse {
+    fn respond_to(self, _: &'r Request<'_>) -> response::Result<'static> {
+        let status = Status::from_code(self.status).unwrap_or(Status::InternalServerError);
+        let content_type =
+            ContentType::parse_flexible(self.content_type).unwrap_or(ContentType::XML);
+
+        return Response::build()
+            .status(status)
+            .header(content_type)
+            .sized_body(self.body.len(), Cursor::new(self.body))
+            .ok();
+    }
+}
+
+/// The API endpoint (e.g. `/api?t=...`); Rocket routes without a query match any query, so this gets every request to the API
+#[get("/")]
+pub(crate) async fn endpoint(conf: &State<Config>, request: ApiRequest) -> ApiResponse {
+    return api::handle(conf, request).await;
+}
+
+/// Responds to requests under the API's path that don't match the endpoint (e.g. `/api/foo`) with error `202`, instead of Rocket's HTML 404 page
+#[catch(404)]
+pub(crate) fn not_found() -> ApiResponse {
+    return api::error_response(TorznabError::NoSuchFunction);
+}
+
+/// Creates Rocket's config, with the [`ServerOptions`] that are set merged over Rocket's usual config (env vars, `Rocket.toml`, etc.)
+pub(crate) fn figment(options: &ServerOptions) -> Result<Figment, RunError> {
+    if options.unix_socket.is_some() {
+        return Err(RunError::UnsupportedOption(
+            "Rocket 0.5 can't listen on Unix sockets; use the axum adapter instead".to_string(),
+        ));
+    }
+
+    let mut figment = rocket::Config::figment();
+    if let Some(address) = options.address {
+        figment = figment.merge(("address", address));
+    }
+    if let Some(port) = options.port {
+        figment = figment.merge(("port", port));
+    }
+    if let Some(tls) = &options.tls {
+        if !cfg!(feature = "tls") {
+            return Err(RunError::UnsupportedOption(
+                "TLS with Rocket requires the `tls` feature".to_string(),
+            ));
+        }
+        figment = figment
+            .merge(("tls.certs", &tls.certs))
+            .merge(("tls.key", &tls.key));
+    }
+
+    return Ok(figment);
+}
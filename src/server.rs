@@ -0,0 +1,130 @@
+//! Options for the server itself (what it listens on, and TLS), for when they should come from your own program's config rather than Rocket's, and a [`ServerHandle`] to control a running server
+//!
+//! Pass them to [`run_with_options`](crate::run_with_options) or [`spawn`](crate::spawn) (or the axum adapter's equivalents). Anything left as `None` falls back to the adapter's default; for Rocket, that's its usual config (`ROCKET_ADDRESS`, `ROCKET_PORT`, `Rocket.toml`, etc.).
+//!
+//! Example:
+//! ```
+//! use torznab_toolkit::server::{ServerOptions, TlsOptions};
+//! use std::net::{IpAddr, Ipv4Addr};
+//!
+//! let options = ServerOptions {
+//!     address: Some(IpAddr::V4(Ipv4Addr::UNSPECIFIED)),
+//!     port: Some(9117),
+//!     tls: Some(TlsOptions {
+//!         certs: "/etc/torznab/cert.pem".into(),
+//!         key: "/etc/torznab/key.pem".into(),
+//!     }),
+//!     ..Default::default()
+//! };
+//! ```
+use crate::RunError;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
+use tokio::task::JoinHandle;
+
+/// The address that's used if [`ServerOptions::address`] isn't set and the adapter doesn't have its own config
+pub const DEFAULT_ADDRESS: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+/// The port that's used if [`ServerOptions::port`] isn't set and the adapter doesn't have its own config
+pub const DEFAULT_PORT: u16 = 8000;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+/// What the server listens on, and whether it uses TLS
+pub struct ServerOptions {
+    /// The IP address to listen on; defaults to [`DEFAULT_ADDRESS`]
+    pub address: Option<IpAddr>,
+    /// The port to listen on; defaults to [`DEFAULT_PORT`]
+    ///
+    /// `0` picks any free port.
+    pub port: Option<u16>,
+    /// A Unix socket to listen on, instead of [`address`](ServerOptions::address) and [`port`](ServerOptions::port)
+    ///
+    /// Only supported by the axum adapter on Unix, since Rocket 0.5 can't listen on Unix sockets; with Rocket, this results in [`RunError::UnsupportedOption`].
+    /// The socket file must not already exist.
+    pub unix_socket: Option<PathBuf>,
+    /// The certificate and key to serve HTTPS with; defaults to plain HTTP
+    ///
+    /// Only supported by Rocket, with the `tls` feature enabled; otherwise, this results in [`RunError::UnsupportedOption`].
+    pub tls: Option<TlsOptions>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The certificate chain and private key for TLS
+pub struct TlsOptions {
+    /// The path to the PEM-encoded certificate chain
+    pub certs: PathBuf,
+    /// The path to the PEM-encoded private key (PKCS#1, PKCS#8, or SEC1)
+    pub key: PathBuf,
+}
+
+/// A handle to a server started in the background with [`spawn`](crate::spawn) (or the axum adapter's equivalent)
+///
+/// Dropping the handle doesn't stop the server; use [`shutdown`](ServerHandle::shutdown) for that.
+pub struct ServerHandle {
+    /// The address the server actually bound to
+    address: Option<SocketAddr>,
+    /// Tells the server to shut down gracefully; `None` once it's been called
+    shutdown: Option<Box<dyn FnOnce() + Send>>,
+    /// The task running the server
+    task: JoinHandle<Result<(), RunError>>,
+}
+
+impl ServerHandle {
+    /// Creates a handle for the server running in `task`, with `shutdown` telling it to shut down gracefully
+    #[cfg(any(feature = "rocket", feature = "axum"))]
+    pub(crate) fn new(
+        address: Option<SocketAddr>,
+        shutdown: impl FnOnce() + Send + 'static,
+        task: JoinHandle<Result<(), RunError>>,
+    ) -> ServerHandle {
+        return ServerHandle {
+            address,
+            shutdown: Some(Box::new(shutdown)),
+            task,
+        };
+    }
+
+    /// Returns the address the server is listening on
+    ///
+    /// This is the actual address, so with port `0` it's the port that was picked. It's `None` if the server is listening on a Unix socket.
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        return self.address;
+    }
+
+    /// Gracefully shuts down the server, waiting for it to stop
+    ///
+    /// New connections are refused, and in-flight requests (e.g. searches) are given a chance to finish first; with Rocket, that's limited by its `shutdown.grace` and `shutdown.mercy` config.
+    pub async fn shutdown(mut self) -> Result<(), RunError> {
+        if let Some(shutdown) = self.shutdown.take() {
+            shutdown();
+        }
+        return self.wait().await;
+    }
+
+    /// Waits for the server to stop by itself (e.g. on Ctrl-C, for Rocket), without telling it to shut down
+    ///
+    /// Returns the error it stopped with, if any.
+    pub async fn wait(self) -> Result<(), RunError> {
+        match self.task.await {
+            Ok(result) => {
+                return result;
+            }
+            Err(e) if e.is_panic() => {
+                std::panic::resume_unwind(e.into_panic());
+            }
+            Err(_) => {
+                // cancelled, which only happens if the runtime is shutting down
+                return Ok(());
+            }
+        }
+    }
+}
+
+impl fmt::Debug for ServerHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return f
+            .debug_struct("ServerHandle")
+            .field("address", &self.address)
+            .finish_non_exhaustive();
+    }
+}
@@ -0,0 +1,247 @@
+//! Checks for a [`Config`] (and its [`Caps`]), so that misconfigurations are caught at startup rather than by clients
+//!
+//! [`run`](crate::run) calls [`Config::validate`] before launching the server, and refuses to start if there are any problems.
+use crate::data::*;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A problem with a [`Config`], as found by [`Config::validate`]
+pub enum ConfigProblem {
+    /// The default `limit` is greater than the max, or the max is 0
+    InvalidLimits {
+        /// The max limit
+        max: u32,
+        /// The default limit
+        default: u32,
+    },
+    /// A [`SearchInfo::search_type`] isn't one of the known types ([`SEARCH_TYPES`])
+    UnknownSearchType(String),
+    /// A search type is listed more than once in [`Caps::searching`]
+    DuplicateSearchType(String),
+    /// A subcategory's ID isn't under its parent category's (i.e. not `xxyy` for category `xx00`)
+    SubcategoryOutsideParent {
+        /// The ID of the parent category
+        category_id: u32,
+        /// The ID of the subcategory
+        subcategory_id: u32,
+    },
+    /// A (sub)category ID is used more than once
+    DuplicateCategoryId(u32),
+    /// A genre is for a category that isn't listed in [`Caps::categories`]
+    UnknownGenreCategory {
+        /// The ID of the genre
+        genre_id: u32,
+        /// The category ID the genre is for
+        category_id: u32,
+    },
+    /// A genre ID is used more than once
+    DuplicateGenreId(u32),
+    /// A tag name is used more than once
+    DuplicateTagName(String),
+}
+
+impl fmt::Display for ConfigProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigProblem::InvalidLimits { max, default } => {
+                return write!(
+                    f,
+                    "invalid limits: default ({}) must be between 1 and max ({})",
+                    default, max
+                );
+            }
+            ConfigProblem::UnknownSearchType(search_type) => {
+                return write!(
+                    f,
+                    "unknown search type `{}`; must be one of {}",
+                    search_type,
+                    SEARCH_TYPES.join(", ")
+                );
+            }
+            ConfigProblem::DuplicateSearchType(search_type) => {
+                return write!(f, "search type `{}` is listed more than once", search_type);
+            }
+            ConfigProblem::SubcategoryOutsideParent {
+                category_id,
+                subcategory_id,
+            } => {
+                return write!(
+                    f,
+                    "subcategory {} isn't under its parent category {}",
+                    subcategory_id, category_id
+                );
+            }
+            ConfigProblem::DuplicateCategoryId(id) => {
+                return write!(f, "category ID {} is used more than once", id);
+            }
+            ConfigProblem::UnknownGenreCategory {
+                genre_id,
+                category_id,
+            } => {
+                return write!(
+                    f,
+                    "genre {} is for category {}, which isn't listed",
+                    genre_id, category_id
+                );
+            }
+            ConfigProblem::DuplicateGenreId(id) => {
+                return write!(f, "genre ID {} is used more than once", id);
+            }
+            ConfigProblem::DuplicateTagName(name) => {
+                return write!(f, "tag `{}` is listed more than once", name);
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigProblem {}
+
+impl Config {
+    /// Checks the config for problems, returning all of them if there are any
+    ///
+    /// Currently, this just checks the capabilities; see [`Caps::validate`]
+    pub fn validate(&self) -> Result<(), Vec<ConfigProblem>> {
+        return self.caps.validate();
+    }
+}
+
+impl Caps {
+    /// Checks the capabilities for problems, returning all of them if there are any
+    ///
+    /// Checks that:
+    /// - the default limit is between 1 and the max limit
+    /// - each search type is one of the known types, and only listed once
+    /// - each subcategory's ID is under its parent's (e.g. `5040` for `5000`)
+    /// - (sub)category IDs, genre IDs, and tag names are unique
+    /// - each genre is for a listed (sub)category
+    pub fn validate(&self) -> Result<(), Vec<ConfigProblem>> {
+        let mut problems: Vec<ConfigProblem> = Vec::new();
+
+        if self.limits.max == 0 || self.limits.default == 0 || self.limits.default > self.limits.max
+        {
+            problems.push(ConfigProblem::InvalidLimits {
+                max: self.limits.max,
+                default: self.limits.default,
+            });
+        }
+
+        let mut search_types: Vec<&str> = Vec::new();
+        for search_info in &self.searching {
+            let search_type = search_info.search_type.as_str();
+            if !SEARCH_TYPES.contains(&search_type) {
+                problems.push(ConfigProblem::UnknownSearchType(search_type.to_string()));
+            }
+            if search_types.contains(&search_type) {
+                problems.push(ConfigProblem::DuplicateSearchType(search_type.to_string()));
+            }
+            search_types.push(search_type);
+        }
+
+        let mut category_ids: Vec<u32> = Vec::new();
+        for category in &self.categories {
+            if category_ids.contains(&category.id) {
+                problems.push(ConfigProblem::DuplicateCategoryId(category.id));
+            }
+            category_ids.push(category.id);
+
+            for subcategory in &category.subcategories {
+                if subcategory.id / 1000 != category.id / 1000 || subcategory.id == category.id {
+                    problems.push(ConfigProblem::SubcategoryOutsideParent {
+                        category_id: category.id,
+                        subcategory_id: subcategory.id,
+                    });
+                }
+                if category_ids.contains(&subcategory.id) {
+                    problems.push(ConfigProblem::DuplicateCategoryId(subcategory.id));
+                }
+                category_ids.push(subcategory.id);
+            }
+        }
+
+        if let Some(genres) = &self.genres {
+            let mut genre_ids: Vec<u32> = Vec::new();
+            for genre in genres {
+                if genre_ids.contains(&genre.id) {
+                    problems.push(ConfigProblem::DuplicateGenreId(genre.id));
+                }
+                genre_ids.push(genre.id);
+
+                if !category_ids.contains(&genre.category_id) {
+                    problems.push(ConfigProblem::UnknownGenreCategory {
+                        genre_id: genre.id,
+                        category_id: genre.category_id,
+                    });
+                }
+            }
+        }
+
+        if let Some(tags) = &self.tags {
+            let mut tag_names: Vec<&str> = Vec::new();
+            for tag in tags {
+                if tag_names.contains(&tag.name.as_str()) {
+                    problems.push(ConfigProblem::DuplicateTagName(tag.name.clone()));
+                }
+                tag_names.push(tag.name.as_str());
+            }
+        }
+
+        if problems.is_empty() {
+            return Ok(());
+        }
+        return Err(problems);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_every_problem() {
+        let caps = Caps {
+            server_info: None,
+            limits: Limits {
+                max: 10,
+                default: 20,
+            },
+            searching: vec![SearchInfo {
+                search_type: "tvsearch".to_string(),
+                available: true,
+                supported_params: vec!["q".to_string()],
+            }],
+            categories: vec![Category {
+                id: 5000,
+                name: "TV".to_string(),
+                subcategories: vec![Subcategory {
+                    id: 2040,
+                    name: "HD".to_string(),
+                }],
+            }],
+            genres: Some(vec![Genre {
+                id: 1,
+                category_id: 7000,
+                name: "Kids".to_string(),
+            }]),
+            tags: None,
+        };
+
+        assert_eq!(
+            caps.validate(),
+            Err(vec![
+                ConfigProblem::InvalidLimits {
+                    max: 10,
+                    default: 20
+                },
+                ConfigProblem::UnknownSearchType("tvsearch".to_string()),
+                ConfigProblem::SubcategoryOutsideParent {
+                    category_id: 5000,
+                    subcategory_id: 2040
+                },
+                ConfigProblem::UnknownGenreCategory {
+                    genre_id: 1,
+                    category_id: 7000
+                },
+            ])
+        );
+    }
+}